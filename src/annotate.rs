@@ -0,0 +1,163 @@
+use crate::x11_safe::CursorImage;
+use crate::Rect;
+use crate::{Point, RGB};
+use image::{ImageBuffer, Pixel, RgbImage};
+
+// same geometry as XDrawRectangle with a wide line: the stroke is centred on
+// the rectangle's edges so half of it falls outside the selection
+pub fn draw_outline(image: &mut RgbImage, min: Point, max: Point, colour: RGB, line_width: i32) {
+    let half = line_width / 2;
+    let (outer_min, outer_max) = (
+        Point::new(min.x - half, min.y - half),
+        Point::new(max.x + line_width - half, max.y + line_width - half),
+    );
+    let (inner_min, inner_max) = (
+        Point::new(min.x + line_width - half, min.y + line_width - half),
+        Point::new(max.x - half, max.y - half),
+    );
+
+    let pixel = image::Rgb([colour.r, colour.g, colour.b]);
+    let x_range = outer_min.x.max(0)..outer_max.x.min(image.width() as i32);
+    let y_range = outer_min.y.max(0)..outer_max.y.min(image.height() as i32);
+
+    for y in y_range {
+        for x in x_range.clone() {
+            let inside = x >= inner_min.x && x < inner_max.x && y >= inner_min.y && y < inner_max.y;
+            if !inside {
+                image.put_pixel(x as u32, y as u32, pixel);
+            }
+        }
+    }
+}
+
+// which corner of the image --stamp goes in
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl std::str::FromStr for Corner {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "top-left" => Ok(Self::TopLeft),
+            "top-right" => Ok(Self::TopRight),
+            "bottom-left" => Ok(Self::BottomLeft),
+            "bottom-right" => Ok(Self::BottomRight),
+            _ => Err("expected top-left, top-right, bottom-left or bottom-right".to_owned()),
+        }
+    }
+}
+
+// which pixels of a line of text are lit, row by row
+pub struct TextMask {
+    pub width: i32,
+    pub height: i32,
+    pub bits: Vec<bool>,
+}
+
+// the text on a darkened box so it reads over anything, each lit pixel drawn
+// as a block x block square. only the colour channels are touched, alpha is
+// made opaque under the text
+pub fn stamp<P: Pixel<Subpixel = u8>>(
+    image: &mut ImageBuffer<P, Vec<u8>>,
+    text: &TextMask,
+    corner: Corner,
+    colour: RGB,
+    block: i32,
+) {
+    let padding = 2 * block;
+    let (width, height) = (
+        text.width * block + 2 * padding,
+        text.height * block + 2 * padding,
+    );
+    let (image_width, image_height) = (image.width() as i32, image.height() as i32);
+    let x = match corner {
+        Corner::TopLeft | Corner::BottomLeft => padding,
+        Corner::TopRight | Corner::BottomRight => image_width - width - padding,
+    };
+    let y = match corner {
+        Corner::TopLeft | Corner::TopRight => padding,
+        Corner::BottomLeft | Corner::BottomRight => image_height - height - padding,
+    };
+
+    for py in y.max(0)..(y + height).min(image_height) {
+        for px in x.max(0)..(x + width).min(image_width) {
+            let (column, row) = ((px - x - padding) / block, (py - y - padding) / block);
+            let lit = px >= x + padding
+                && py >= y + padding
+                && column < text.width
+                && row < text.height
+                && text.bits[(row * text.width + column) as usize];
+
+            let channels = image.get_pixel_mut(px as u32, py as u32).channels_mut();
+            if lit {
+                channels[..3].copy_from_slice(&[colour.r, colour.g, colour.b]);
+                if let Some(alpha) = channels.get_mut(3) {
+                    *alpha = 255;
+                }
+            } else {
+                for channel in &mut channels[..3] {
+                    *channel /= 2;
+                }
+            }
+        }
+    }
+}
+
+// gaussian blur over each rect, clipped to the image. rects are in the
+// image's own coordinates
+pub fn redact<P: Pixel<Subpixel = u8> + 'static>(
+    image: &mut ImageBuffer<P, Vec<u8>>,
+    rects: &[Rect],
+    strength: f32,
+) {
+    let bounds = Rect::new(0, 0, image.width() as i32, image.height() as i32);
+    for rect in rects.iter().filter_map(|rect| rect.intersect(&bounds)) {
+        let area = image::imageops::crop_imm(
+            image,
+            rect.x as u32,
+            rect.y as u32,
+            rect.width as u32,
+            rect.height as u32,
+        )
+        .to_image();
+        let blurred = image::imageops::blur(&area, strength);
+        image::imageops::replace(image, &blurred, rect.x as i64, rect.y as i64);
+    }
+}
+
+// the cursor is premultiplied so blending is src + dst * (1 - alpha), origin
+// is where the image's top left corner sits on the root
+// works on RGB and RGBA alike, alpha composites the same way as colour
+pub fn draw_cursor<P: Pixel<Subpixel = u8>>(
+    image: &mut ImageBuffer<P, Vec<u8>>,
+    cursor: &CursorImage,
+    origin: Point,
+) {
+    for row in 0..cursor.height {
+        for column in 0..cursor.width {
+            let x = cursor.x - origin.x + column as i32;
+            let y = cursor.y - origin.y + row as i32;
+            if x < 0 || y < 0 || x >= image.width() as i32 || y >= image.height() as i32 {
+                continue;
+            }
+
+            let argb = cursor.pixels[(row * cursor.width + column) as usize];
+            let alpha = argb >> 24;
+            if alpha == 0 {
+                continue;
+            }
+
+            let pixel = image.get_pixel_mut(x as u32, y as u32);
+            for (channel, shift) in pixel.channels_mut().iter_mut().zip([16, 8, 0, 24]) {
+                let source = (argb >> shift) & 0xff;
+                *channel = (source + *channel as u32 * (255 - alpha) / 255).min(255) as u8;
+            }
+        }
+    }
+}
@@ -0,0 +1,717 @@
+use crate::annotate::Corner;
+use crate::jpeg::Subsampling;
+use crate::log::Level;
+use crate::rotation::RotationMode;
+use crate::scale::{self, Scale};
+use crate::{config, x11_safe};
+use crate::{
+    Ratio, Rect, Shape, DEFAULT_LINE_COLOUR, MAX_LINE_WIDTH, REFRESH_RATE, REFRESH_RATES, RGB,
+};
+use image::imageops::FilterType;
+use x11::keysym::{XK_Escape, XK_KP_Enter, XK_Return, XK_f};
+
+const DEFAULT_QUALITY: u8 = 90;
+// what slurp prints, so sleek can stand in for it
+const DEFAULT_GEOMETRY_FORMAT: &str = "%x,%y %wx%h";
+const DEFAULT_SNAP_THRESHOLD: i32 = 8;
+const DEFAULT_LOUPE_SIZE: i32 = 15;
+const DEFAULT_LOUPE_ZOOM: i32 = 8;
+const DEFAULT_GRID_DIVISIONS: i32 = 3;
+const DEFAULT_STAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+const DEFAULT_BLUR_STRENGTH: f32 = 8.0;
+
+const USAGE: &str = "\
+usage: sleek [options] [template]
+
+template is a strftime pattern for the output file, default
+sleek-%Y-%m-%d:%H:%M:%S. its extension picks the format (.png, .jpg/.jpeg,
+.webp, .bmp, .ppm), anything else gets .png appended. - writes the image to stdout.
+
+defaults for line_colour, line_width, template, output_dir, quality,
+clipboard and current_monitor can be set in $XDG_CONFIG_HOME/sleek/config.toml
+(~/.config/sleek/config.toml) as key = value lines, options given here
+override them.
+
+capture:
+  --fullscreen                 save the whole screen without selecting
+  --relative-to-active X,Y,W,H capture a rect relative to the focused window,
+                               negative X/Y count from its right/bottom edge
+  --geometry GEOMETRY          capture \"x,y,w,h\", \"x,y wxh\" or WxH+X+Y without
+                               the overlay, - reads it from stdin
+  --initial-geometry GEOMETRY  start with \"x,y,w,h\" or \"x,y wxh\" selected, on
+                               the root like --print-geometry. - reads it from
+                               stdin
+  --last                       start with the previous saved selection selected,
+                               Enter captures it again
+  --window                     click a window to capture it, frame included
+  --active                     capture the active window, frame included unless
+                               --client-only, the whole screen if none is active
+  --window-id ID               capture one window directly, keeps ARGB alpha
+  --wait-for-pid PID           capture the first window mapped by PID
+  --include-decorations        --window-id/--active include the frame (default)
+  --client-only                --window-id/--active capture the client area only
+  --monitor N                  restrict the overlay to one monitor, see --doctor
+  --current-monitor            restrict the overlay to the monitor the pointer
+                               is on
+  --all-monitors               cover every monitor, overriding current_monitor
+                               in the config (default)
+  --screen-number N            X screen to use (default: the default screen).
+                               X screens (:0.0, :0.1) each have their own root
+                               and are not the RandR monitors of --monitor
+  --delay SECONDS              count down before snapshotting the screen
+  --wait-for-key KEYSYM        wait for a global key press before starting
+  --timeout SECONDS            give up on --wait-for-key/--wait-for-pid
+                               (default: wait forever)
+  --click-through KEYSYM       leave the overlay click through until KEYSYM
+  --continuous                 after each save snapshot the screen again and wait
+                               for the next selection, until ESC
+  --count N                    --continuous, stopping after N saves
+  --monitor-region             save the region each time it settles after a change
+  --max-count N                stop --monitor-region after N saves (default: 100)
+  --max-duration SECONDS       stop --monitor-region after SECONDS (default: 600)
+
+output:
+  --quality N                  JPEG quality, 1-100 (default: 90)
+  --jpeg-subsampling 444|422|420
+                               JPEG chroma subsampling, 444 keeps text sharp and
+                               420 is smaller for photos (default: 444)
+  --jpeg-progressive           write progressive JPEGs
+  --cursor                     include the mouse cursor, needs XFixes
+  --alpha                      keep the alpha channel of a 32 bit visual
+  --rotation-mode bake|tag     bake rotated monitors into the pixels or tag the
+                               PNG with EXIF orientation (default: bake)
+  --stream-header              prefix the image with a SLEEK1 header line when
+                               writing to stdout
+  --metadata                   store the time, geometry, monitor and hostname in
+                               PNG text chunks
+  --output-dir DIR             put the file in DIR, created if missing. an
+                               absolute template ignores it
+  --date-folders               save into YYYY/MM/DD below the template's directory
+  --resolutions N,N,...        save NxN copies for icon sets
+  --scale FACTOR               scale the selection before saving, e.g. 0.5
+  --resize WxH                 resize the selection before saving, Wx or xH keeps
+                               the aspect ratio
+  --filter NAME                resampling for --scale/--resize: nearest, triangle,
+                               catmullrom, gaussian or lanczos3 (default: lanczos3)
+  --selection-outline-only     save the whole screen with the selection outlined
+  --also-crop                  with --selection-outline-only, save the crop too
+  --stamp                      draw the time into the saved selection
+  --stamp-format FORMAT        strftime format for --stamp
+                               (default: %Y-%m-%d %H:%M:%S)
+  --stamp-corner CORNER        top-left, top-right, bottom-left or bottom-right
+                               (default: top-left)
+  --stamp-colour RRGGBB        colour of the --stamp text (default: ffffff)
+  --sidecar                    write capture metadata to a .json next to the image
+  --print-geometry             print the selection as \"x,y wxh\" instead of saving
+  --geometry-format FORMAT     --print-geometry with %x, %y, %w and %h in FORMAT
+  --emit-events                follow the selection on stdout while it's made:
+                               \"MOVE x y\", \"SELECT x y w h\" and
+                               \"COMMIT x y w h\", one per line
+  --exec COMMAND               run COMMAND after each saved file, {} is its path
+  --exec-wait                  wait for --exec to finish before exiting
+  --exec-shell                 run --exec with sh -c instead of splitting it
+  --notify                     show a desktop notification for each saved file
+  --clipboard                  serve the capture on the clipboard, also saved to a
+                               file when a template is given
+  --copy-path                  put the saved file's absolute path on the
+                               clipboard, alongside the image with --clipboard
+  --shm-name NAME              hand the capture over as raw RGBA in shared memory
+
+overlay:
+  --line-colour RRGGBB         selection colour (default: #800080)
+  --line-width N               selection line width, 1-50 (default: 5)
+  --overlay-rounded            draw the selection with rounded corners
+  --blur-strength SIGMA        how hard areas Shift dragged over a finished
+                               selection are blurred on save (default: 8)
+  --no-scale                   keep the default line width and loupe zoom at their
+                               96 DPI sizes on denser displays
+  --crosshair                  draw guide lines across the screen through the
+                               pointer
+  --grid                       divide the selection with guides while dragging,
+                               never saved
+  --grid-divisions N           --grid, with N x N cells (default: 3)
+  --radius PIXELS              round the selection's corners and make the cut off
+                               parts transparent in the capture, capped at half
+                               the shorter side
+  --shape rectangle|ellipse    ellipse saves the selection's bounding box with
+                               everything outside the ellipse transparent, which
+                               needs PNG or WebP (default: rectangle)
+  --center-on-region           centre the size label on the selection
+  --minimal                    only a thin outline, no labels or dimming
+  --no-freeze                  select over the live screen and capture it as it
+                               is when the selection is saved. nothing is dimmed,
+                               and anything that changes under the outline while
+                               it's drawn may not be fully repainted in time
+  --ratio W:H                  keep the selection at this aspect ratio
+  --min-size PIXELS            smaller selections are discarded (default: 1)
+  --loupe                      show a magnified view of the pixels by the pointer
+  --loupe-size PIXELS          width of the magnified area (default: 15)
+  --loupe-zoom N               magnification of the loupe (default: 8)
+  --confirm-key KEYSYM         key that saves the selection (default: Return)
+  --cancel-key KEYSYM          key that clears the selection or quits
+                               (default: Escape)
+  --fullscreen-key KEYSYM      key that saves the whole screen straight away,
+                               whatever is selected (default: f)
+  --no-snap                    don't snap the dragged corner to window edges
+  --snap-threshold PIXELS      how close an edge has to be to snap (default: 8)
+  --refresh-rate N             redraws per second while dragging, 10-240. lower
+                               helps over slow remote connections (default: 60)
+
+other:
+  --pick                       print the colour of the clicked pixel as #rrggbb
+  --pick-rgb                   with --pick, also print it as rgb(r, g, b)
+  --palette-pick               click to collect colours, Enter prints them
+  --swatch-out FILE            with --palette-pick, save the swatches as a PNG
+  --list-screens               list X screens, not monitors, and exit
+  --doctor                     report what the X server supports and exit
+  --force                      always capture through XWayland under Wayland,
+                               and without the warning
+  -q, --quiet                  don't print the saved path
+  -v, --verbose                describe each step of the capture on stderr
+  -h, --help                   show this help and exit
+  -V, --version                show the version and exit";
+
+pub struct Args {
+    pub template: Option<String>,
+    pub rotation_mode: RotationMode,
+    pub relative_to_active: Option<Rect>,
+    pub stream_header: bool,
+    pub palette_pick: bool,
+    pub swatch_out: Option<String>,
+    pub monitor_region: bool,
+    pub max_count: u32,
+    pub continuous: bool,
+    pub count: Option<u32>,
+    pub max_duration: u64,
+    pub overlay_rounded: bool,
+    pub grid: Option<i32>,
+    pub crosshair: bool,
+    pub dpi_scale: bool,
+    pub blur_strength: f32,
+    pub radius: Option<i32>,
+    pub shape: Shape,
+    pub screen_number: Option<i32>,
+    pub list_screens: bool,
+    pub center_on_region: bool,
+    pub resolutions: Vec<u32>,
+    pub scale: Option<Scale>,
+    pub filter: FilterType,
+    pub selection_outline_only: bool,
+    pub also_crop: bool,
+    pub wait_for_key: Option<String>,
+    pub timeout: Option<u64>,
+    pub initial_geometry: Option<String>,
+    pub last: bool,
+    pub output_dir: Option<String>,
+    pub date_folders: bool,
+    pub window_id: Option<u64>,
+    pub minimal: bool,
+    pub freeze: bool,
+    pub client_only: bool,
+    pub wait_for_pid: Option<u32>,
+    pub sidecar: bool,
+    pub metadata: bool,
+    pub doctor: bool,
+    pub click_through: Option<String>,
+    pub shm_name: Option<String>,
+    pub line_colour: RGB,
+    pub line_width: Option<i32>,
+    pub clipboard: bool,
+    pub copy_path: bool,
+    pub monitor: Option<usize>,
+    pub current_monitor: bool,
+    pub fullscreen: bool,
+    pub delay: Option<u64>,
+    pub quality: u8,
+    pub jpeg_subsampling: Subsampling,
+    pub jpeg_progressive: bool,
+    pub cursor: bool,
+    pub window_pick: bool,
+    pub active: bool,
+    pub snap: bool,
+    pub snap_threshold: i32,
+    pub ratio: Option<Ratio>,
+    pub min_size: i32,
+    pub geometry: Option<String>,
+    pub print_geometry: Option<String>,
+    pub emit_events: bool,
+    pub alpha: bool,
+    pub loupe: bool,
+    pub loupe_size: i32,
+    pub loupe_zoom: i32,
+    pub pick: bool,
+    pub pick_rgb: bool,
+    pub exec: Option<String>,
+    pub exec_wait: bool,
+    pub exec_shell: bool,
+    pub notify: bool,
+    pub confirm_key: Option<u64>,
+    pub cancel_key: Option<u64>,
+    pub fullscreen_key: Option<u64>,
+    pub verbosity: Level,
+    pub force: bool,
+    pub refresh_rate: u32,
+    pub stamp: bool,
+    pub stamp_format: String,
+    pub stamp_corner: Corner,
+    pub stamp_colour: RGB,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self {
+            template: None,
+            rotation_mode: RotationMode::Bake,
+            relative_to_active: None,
+            stream_header: false,
+            palette_pick: false,
+            swatch_out: None,
+            monitor_region: false,
+            max_count: 100,
+            continuous: false,
+            count: None,
+            max_duration: 600,
+            overlay_rounded: false,
+            grid: None,
+            crosshair: false,
+            dpi_scale: true,
+            blur_strength: DEFAULT_BLUR_STRENGTH,
+            radius: None,
+            shape: Shape::Rectangle,
+            screen_number: None,
+            list_screens: false,
+            center_on_region: false,
+            resolutions: Vec::new(),
+            scale: None,
+            filter: FilterType::Lanczos3,
+            selection_outline_only: false,
+            also_crop: false,
+            wait_for_key: None,
+            timeout: None,
+            initial_geometry: None,
+            last: false,
+            output_dir: None,
+            date_folders: false,
+            window_id: None,
+            minimal: false,
+            freeze: true,
+            client_only: false,
+            wait_for_pid: None,
+            sidecar: false,
+            metadata: false,
+            doctor: false,
+            click_through: None,
+            shm_name: None,
+            line_colour: DEFAULT_LINE_COLOUR,
+            line_width: None,
+            clipboard: false,
+            copy_path: false,
+            monitor: None,
+            current_monitor: false,
+            fullscreen: false,
+            delay: None,
+            quality: DEFAULT_QUALITY,
+            jpeg_subsampling: Subsampling::S444,
+            jpeg_progressive: false,
+            cursor: false,
+            window_pick: false,
+            active: false,
+            snap: true,
+            snap_threshold: DEFAULT_SNAP_THRESHOLD,
+            ratio: None,
+            min_size: 1,
+            geometry: None,
+            print_geometry: None,
+            emit_events: false,
+            alpha: false,
+            loupe: false,
+            loupe_size: DEFAULT_LOUPE_SIZE,
+            loupe_zoom: DEFAULT_LOUPE_ZOOM,
+            pick: false,
+            pick_rgb: false,
+            exec: None,
+            exec_wait: false,
+            exec_shell: false,
+            notify: false,
+            confirm_key: None,
+            cancel_key: None,
+            fullscreen_key: None,
+            verbosity: Level::Normal,
+            force: false,
+            refresh_rate: REFRESH_RATE,
+            stamp: false,
+            stamp_format: DEFAULT_STAMP_FORMAT.to_owned(),
+            stamp_corner: Corner::TopLeft,
+            stamp_colour: RGB::new(255, 255, 255),
+        }
+    }
+}
+
+impl Args {
+    // Enter, either of them, unless --confirm-key says otherwise
+    pub fn is_confirm(&self, keysym: u64) -> bool {
+        match self.confirm_key {
+            Some(key) => keysym == key,
+            None => keysym == XK_Return as u64 || keysym == XK_KP_Enter as u64,
+        }
+    }
+
+    pub fn is_cancel(&self, keysym: u64) -> bool {
+        keysym == self.cancel_key.unwrap_or(XK_Escape as u64)
+    }
+
+    pub fn is_fullscreen(&self, keysym: u64) -> bool {
+        keysym == self.fullscreen_key.unwrap_or(XK_f as u64)
+    }
+
+    pub fn parse() -> Self {
+        let mut args = Self::default();
+        // only reported once it's clear this isn't --help or --version
+        let config = config::apply(&mut args);
+        let args = Self::parse_argv(args, std::env::args().skip(1));
+
+        if let Err(e) = config {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+        args
+    }
+
+    // the command line on top of whatever args already holds
+    fn parse_argv(mut args: Self, argv: impl IntoIterator<Item = String>) -> Self {
+        // the config's template is only a default, the first one given here wins
+        let mut template_given = false;
+
+        let mut argv = argv.into_iter();
+        while let Some(arg) = argv.next() {
+            match arg.as_str() {
+                "--rotation-mode" => {
+                    args.rotation_mode = parse_value(&arg, argv.next());
+                }
+                "--relative-to-active" => {
+                    args.relative_to_active = Some(parse_value(&arg, argv.next()));
+                }
+                "--stream-header" => args.stream_header = true,
+                "--palette-pick" => args.palette_pick = true,
+                "--pick" => args.pick = true,
+                "--pick-rgb" => args.pick_rgb = true,
+                "--swatch-out" => {
+                    args.swatch_out = Some(parse_value(&arg, argv.next()));
+                }
+                "--monitor-region" => args.monitor_region = true,
+                "--continuous" => args.continuous = true,
+                "--count" => {
+                    let count: u32 = parse_value(&arg, argv.next());
+                    if count < 1 {
+                        eprintln!("{arg} must be at least 1");
+                        std::process::exit(1);
+                    }
+                    args.count = Some(count);
+                    args.continuous = true;
+                }
+                "--max-count" => args.max_count = parse_value(&arg, argv.next()),
+                "--max-duration" => args.max_duration = parse_value(&arg, argv.next()),
+                "--overlay-rounded" => args.overlay_rounded = true,
+                "--crosshair" => args.crosshair = true,
+                "--no-scale" => args.dpi_scale = false,
+                "--blur-strength" => {
+                    args.blur_strength = parse_value(&arg, argv.next());
+                    if args.blur_strength.is_nan() || args.blur_strength <= 0.0 {
+                        eprintln!("{arg} must be greater than 0");
+                        std::process::exit(1);
+                    }
+                }
+                // a count of its own would swallow a numeric template
+                "--grid" => args.grid = Some(args.grid.unwrap_or(DEFAULT_GRID_DIVISIONS)),
+                "--grid-divisions" => {
+                    let divisions: i32 = parse_value(&arg, argv.next());
+                    if divisions < 2 {
+                        eprintln!("{arg} must be at least 2");
+                        std::process::exit(1);
+                    }
+                    args.grid = Some(divisions);
+                }
+                "--radius" => {
+                    let radius: i32 = parse_value(&arg, argv.next());
+                    if radius < 1 {
+                        eprintln!("{arg} must be at least 1");
+                        std::process::exit(1);
+                    }
+                    args.radius = Some(radius);
+                }
+                "--shape" => args.shape = parse_value(&arg, argv.next()),
+                "--screen-number" => {
+                    args.screen_number = Some(parse_value(&arg, argv.next()));
+                }
+                "--list-screens" => args.list_screens = true,
+                "--center-on-region" => args.center_on_region = true,
+                "--resolutions" => {
+                    args.resolutions = parse_list(&arg, argv.next());
+                    if args.resolutions.contains(&0) {
+                        eprintln!("{arg} sizes must be at least 1");
+                        std::process::exit(1);
+                    }
+                }
+                "--scale" => args.scale = Some(parse_with(&arg, argv.next(), Scale::factor)),
+                "--resize" => args.scale = Some(parse_with(&arg, argv.next(), Scale::size)),
+                "--filter" => args.filter = parse_with(&arg, argv.next(), scale::filter),
+                "--selection-outline-only" => args.selection_outline_only = true,
+                "--also-crop" => args.also_crop = true,
+                "--wait-for-key" => args.wait_for_key = Some(parse_value(&arg, argv.next())),
+                "--timeout" => args.timeout = Some(parse_value(&arg, argv.next())),
+                "--print-geometry" => {
+                    args.print_geometry
+                        .get_or_insert(DEFAULT_GEOMETRY_FORMAT.to_owned());
+                }
+                "--geometry-format" => {
+                    args.print_geometry = Some(parse_value(&arg, argv.next()));
+                }
+                "--emit-events" => args.emit_events = true,
+                "--geometry" => args.geometry = Some(parse_value(&arg, argv.next())),
+                "--last" => args.last = true,
+                "--initial-geometry" => {
+                    args.initial_geometry = Some(parse_value(&arg, argv.next()));
+                }
+                "--output-dir" => args.output_dir = Some(parse_value(&arg, argv.next())),
+                "--date-folders" => args.date_folders = true,
+                "--window-id" => {
+                    let id: String = parse_value(&arg, argv.next());
+                    args.window_id = Some(parse_window_id(&arg, &id));
+                }
+                "--minimal" => args.minimal = true,
+                "--no-freeze" => args.freeze = false,
+                "--include-decorations" => args.client_only = false,
+                "--client-only" => args.client_only = true,
+                "--wait-for-pid" => args.wait_for_pid = Some(parse_value(&arg, argv.next())),
+                "--sidecar" => args.sidecar = true,
+                "--metadata" => args.metadata = true,
+                "--doctor" => args.doctor = true,
+                "--click-through" => args.click_through = Some(parse_value(&arg, argv.next())),
+                "--shm-name" => args.shm_name = Some(parse_value(&arg, argv.next())),
+                "--line-colour" => {
+                    let colour: String = parse_value(&arg, argv.next());
+                    // a typo in the colour shouldn't stop a screenshot from being taken
+                    match colour.parse() {
+                        Ok(colour) => args.line_colour = colour,
+                        Err(e) => {
+                            eprintln!("invalid value for {arg}: {e}, using {}", args.line_colour)
+                        }
+                    }
+                }
+                "--line-width" => args.line_width = Some(parse_with(&arg, argv.next(), line_width)),
+                "--confirm-key" => args.confirm_key = Some(parse_keysym(&arg, argv.next())),
+                "--cancel-key" => args.cancel_key = Some(parse_keysym(&arg, argv.next())),
+                "--fullscreen-key" => args.fullscreen_key = Some(parse_keysym(&arg, argv.next())),
+                "--exec" => args.exec = Some(parse_value(&arg, argv.next())),
+                "--exec-wait" => args.exec_wait = true,
+                "--exec-shell" => args.exec_shell = true,
+                "--notify" => args.notify = true,
+                "-q" | "--quiet" => args.verbosity = Level::Quiet,
+                "-v" | "--verbose" => args.verbosity = Level::Verbose,
+                "--force" => args.force = true,
+                "--clipboard" => args.clipboard = true,
+                "--copy-path" => args.copy_path = true,
+                "--monitor" => args.monitor = Some(parse_value(&arg, argv.next())),
+                "--current-monitor" => args.current_monitor = true,
+                "--all-monitors" => args.current_monitor = false,
+                "--fullscreen" => args.fullscreen = true,
+                "--delay" => args.delay = Some(parse_value(&arg, argv.next())),
+                "--jpeg-subsampling" => {
+                    args.jpeg_subsampling = parse_value(&arg, argv.next());
+                }
+                "--jpeg-progressive" => args.jpeg_progressive = true,
+                "--quality" => {
+                    args.quality = parse_value(&arg, argv.next());
+                    if !(1..=100).contains(&args.quality) {
+                        eprintln!("{arg} must be between 1 and 100");
+                        std::process::exit(1);
+                    }
+                }
+                "--cursor" => args.cursor = true,
+                "--alpha" => args.alpha = true,
+                "--window" => args.window_pick = true,
+                "--active" => args.active = true,
+                "--no-snap" => args.snap = false,
+                "--loupe" => args.loupe = true,
+                "--loupe-size" => {
+                    args.loupe_size = parse_value(&arg, argv.next());
+                    if args.loupe_size < 1 {
+                        eprintln!("{arg} must be at least 1");
+                        std::process::exit(1);
+                    }
+                }
+                "--loupe-zoom" => {
+                    args.loupe_zoom = parse_value(&arg, argv.next());
+                    if args.loupe_zoom < 1 {
+                        eprintln!("{arg} must be at least 1");
+                        std::process::exit(1);
+                    }
+                }
+                "--min-size" => {
+                    args.min_size = parse_value(&arg, argv.next());
+                    if args.min_size < 1 {
+                        eprintln!("{arg} must be at least 1");
+                        std::process::exit(1);
+                    }
+                }
+                "--ratio" => args.ratio = Some(parse_value(&arg, argv.next())),
+                "--snap-threshold" => {
+                    args.snap_threshold = parse_value(&arg, argv.next());
+                    if args.snap_threshold < 0 {
+                        eprintln!("{arg} can't be negative");
+                        std::process::exit(1);
+                    }
+                }
+                "--stamp" => args.stamp = true,
+                "--stamp-format" => {
+                    args.stamp = true;
+                    args.stamp_format = parse_with(&arg, argv.next(), strftime);
+                }
+                "--stamp-corner" => {
+                    args.stamp = true;
+                    args.stamp_corner = parse_value(&arg, argv.next());
+                }
+                "--stamp-colour" => {
+                    args.stamp = true;
+                    args.stamp_colour = parse_value(&arg, argv.next());
+                }
+                "--refresh-rate" => {
+                    args.refresh_rate = parse_value(&arg, argv.next());
+                    if !REFRESH_RATES.contains(&args.refresh_rate) {
+                        eprintln!(
+                            "{arg} must be between {} and {}",
+                            REFRESH_RATES.start(),
+                            REFRESH_RATES.end()
+                        );
+                        std::process::exit(1);
+                    }
+                }
+                "-h" | "--help" => {
+                    println!("{USAGE}");
+                    std::process::exit(0);
+                }
+                "-V" | "--version" => {
+                    println!("sleek {}", env!("CARGO_PKG_VERSION"));
+                    std::process::exit(0);
+                }
+                // a mistyped flag would otherwise silently become the template
+                flag if flag.starts_with('-') && flag != "-" => {
+                    eprintln!("unknown option {flag}, see --help");
+                    std::process::exit(1);
+                }
+                _ => {
+                    if !template_given {
+                        args.template = Some(arg);
+                        template_given = true;
+                    }
+                }
+            }
+        }
+
+        args
+    }
+}
+
+fn parse_value<T: std::str::FromStr>(flag: &str, value: Option<String>) -> T
+where
+    T::Err: std::fmt::Display,
+{
+    let value = value.unwrap_or_else(|| {
+        eprintln!("{flag} requires a value");
+        std::process::exit(1);
+    });
+    value.parse().unwrap_or_else(|e| {
+        eprintln!("invalid value '{value}' for {flag}: {e}");
+        std::process::exit(1);
+    })
+}
+
+// for values that don't have a FromStr of their own
+fn parse_with<T>(flag: &str, value: Option<String>, parse: fn(&str) -> Result<T, String>) -> T {
+    let value: String = parse_value(flag, value);
+    parse(&value).unwrap_or_else(|e| {
+        eprintln!("invalid value '{value}' for {flag}: {e}");
+        std::process::exit(1);
+    })
+}
+
+fn line_width(s: &str) -> Result<i32, String> {
+    let width: i32 = s.parse().map_err(|_| "not a whole number".to_owned())?;
+    if !(1..=MAX_LINE_WIDTH).contains(&width) {
+        return Err(format!("must be between 1 and {MAX_LINE_WIDTH}"));
+    }
+    Ok(width)
+}
+
+// chrono only notices a bad specifier when formatting, by panicking
+pub(crate) fn strftime(s: &str) -> Result<String, String> {
+    use chrono::format::{Item, StrftimeItems};
+    if StrftimeItems::new(s).any(|item| matches!(item, Item::Error)) {
+        return Err("not a valid strftime format".to_owned());
+    }
+    Ok(s.to_owned())
+}
+
+fn parse_list<T: std::str::FromStr>(flag: &str, value: Option<String>) -> Vec<T>
+where
+    T::Err: std::fmt::Display,
+{
+    let value: String = parse_value(flag, value);
+    value
+        .split(',')
+        .map(|item| parse_value(flag, Some(item.trim().to_owned())))
+        .collect()
+}
+
+fn parse_keysym(flag: &str, value: Option<String>) -> u64 {
+    let name: String = parse_value(flag, value);
+    x11_safe::keysym_from_name(&name).unwrap_or_else(|_| {
+        eprintln!("invalid value '{name}' for {flag}: not a keysym name");
+        std::process::exit(1);
+    })
+}
+
+// xwininfo/xdotool print ids in hex, xprop in decimal
+fn parse_window_id(flag: &str, id: &str) -> u64 {
+    let parsed = match id.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => id.parse(),
+    };
+    parsed.unwrap_or_else(|e| {
+        eprintln!("invalid value '{id}' for {flag}: {e}");
+        std::process::exit(1);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_width_range() {
+        assert!(line_width("0").is_err());
+        assert_eq!(line_width("1"), Ok(1));
+        assert_eq!(line_width("50"), Ok(50));
+        assert!(line_width("51").is_err());
+        assert!(line_width("-1").is_err());
+        assert!(line_width("wide").is_err());
+    }
+
+    fn parse(argv: &[&str]) -> Args {
+        Args::parse_argv(Args::default(), argv.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn grid_leaves_numeric_templates_alone() {
+        let args = parse(&["--grid", "2024"]);
+        assert_eq!(args.grid, Some(DEFAULT_GRID_DIVISIONS));
+        assert_eq!(args.template.as_deref(), Some("2024"));
+    }
+
+    #[test]
+    fn grid_divisions_turn_the_grid_on() {
+        assert_eq!(parse(&["--grid-divisions", "5"]).grid, Some(5));
+        assert_eq!(parse(&["--grid-divisions", "5", "--grid"]).grid, Some(5));
+        assert_eq!(parse(&[]).grid, None);
+    }
+}
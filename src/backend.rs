@@ -0,0 +1,45 @@
+use crate::args::Args;
+use crate::window::{self, Capture};
+use crate::{wayland, Rect};
+
+// where a capture of a region known up front comes from. the overlay and the
+// window captures only exist on X, but a plain region can also be asked of a
+// Wayland compositor
+pub trait Backend {
+    // rect in root (or layout) coordinates, None for everything
+    fn capture(&self, rect: Option<Rect>, cursor: bool) -> Result<Capture, String>;
+}
+
+// Xorg, or XWayland when nothing better is available
+pub struct X11;
+
+impl Backend for X11 {
+    // the cursor isn't part of a root capture, only the overlay draws it in
+    fn capture(&self, rect: Option<Rect>, _cursor: bool) -> Result<Capture, String> {
+        // capture_rect cuts the rect down to the screen
+        window::capture_rect(rect.unwrap_or(Rect::new(0, 0, i32::MAX, i32::MAX)))
+    }
+}
+
+// wlr-screencopy, which wlroots compositors offer and GNOME and KDE don't
+pub struct Wayland;
+
+impl Backend for Wayland {
+    fn capture(&self, rect: Option<Rect>, cursor: bool) -> Result<Capture, String> {
+        wayland::capture(rect, cursor)
+    }
+}
+
+// the backends to try in turn for a capture that doesn't need the overlay. the
+// compositor goes first under Wayland, X11 covers --geometry behind it.
+// --fullscreen on X11 goes through the overlay instead so --monitor applies
+pub fn select(args: &Args) -> Vec<Box<dyn Backend>> {
+    let mut backends: Vec<Box<dyn Backend>> = Vec::new();
+    if crate::native_wayland(args) {
+        backends.push(Box::new(Wayland));
+    }
+    if args.geometry.is_some() {
+        backends.push(Box::new(X11));
+    }
+    backends
+}
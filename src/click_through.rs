@@ -0,0 +1,39 @@
+use crate::x11_safe::{self, Event};
+use crate::RenderWindow;
+
+// the overlay starts out transparent with an empty input region so the apps
+// underneath can still be used, the key then snapshots the screen as it is
+// now and hands over to the normal selection
+pub fn wait_to_engage(render_window: &mut RenderWindow, keysym: &str) -> Result<(), String> {
+    let display = render_window.display;
+    let root = render_window.root;
+
+    let keycode = x11_safe::keysym_to_keycode(display, keysym)
+        .map_err(|_| format!("unknown key '{keysym}'"))?;
+
+    x11_safe::grab_key(display, keycode, root);
+    x11_safe::sync(display);
+
+    loop {
+        if let Event::KeyPress {
+            keycode: pressed, ..
+        } = x11_safe::next_event(display)
+        {
+            if pressed == keycode as u32 {
+                break;
+            }
+        }
+    }
+
+    x11_safe::ungrab_key(display, keycode, root);
+
+    render_window.snapshot().map_err(|e| e.to_string())?;
+
+    render_window.repaint();
+    x11_safe::set_click_through(display, render_window.window, false);
+
+    x11_safe::set_input_focus(display, render_window.window);
+    render_window.grab_input();
+
+    Ok(())
+}
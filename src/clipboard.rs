@@ -0,0 +1,173 @@
+use crate::x11_safe::{self, Display, Event};
+use crate::RenderWindow;
+use x11::xlib::{NoEventMask, PropertyChangeMask};
+
+// what's on offer, --clipboard's png and --copy-path's text can come from the
+// same owner so pasting picks whichever it understands
+#[derive(Default)]
+pub struct Contents<'a> {
+    pub png: Option<&'a [u8]>,
+    pub text: Option<&'a str>,
+}
+
+// X has no clipboard storage of its own, the png is only available for as long
+// as sleek keeps running and answering requests for it. this returns once
+// another client takes the clipboard over
+pub fn serve(render_window: &mut RenderWindow, contents: &Contents) -> Result<(), String> {
+    // the overlay has to go but the window stays around to own the selection
+    render_window.release_input();
+    x11_safe::unmap_window(render_window.display, render_window.window);
+
+    answer_requests(render_window.display, render_window.window, contents)
+}
+
+// for when the overlay is already gone or was never up, a window that's never
+// mapped owns the selection instead
+pub fn serve_detached(contents: &Contents) -> Result<(), String> {
+    let connection = x11_safe::open_display().map_err(|e| e.to_string())?;
+    let display = connection.display();
+    let root = x11_safe::root_window(display, x11_safe::default_screen(display));
+    x11_safe::create_popup(display, root, 0, 0, 1, 1, 0)
+        .map_err(|e| e.to_string())
+        .and_then(|window| {
+            let result = answer_requests(display, window, contents);
+            x11_safe::destroy_window(display, window);
+            result
+        })
+}
+
+// a transfer too big for one request, sent a piece at a time as the requestor
+// deletes the property to ask for the next
+struct Incr<'a> {
+    requestor: u64,
+    property: u64,
+    target: u64,
+    pieces: Pieces<'a>,
+}
+
+type Pieces<'a> = std::iter::Chain<std::slice::Chunks<'a, u8>, std::iter::Once<&'a [u8]>>;
+
+// None when data fits in a single property, otherwise the pieces of at most
+// limit bytes to send in turn, ending with the empty one that marks the end
+fn incr_pieces(data: &[u8], limit: usize) -> Option<Pieces<'_>> {
+    (data.len() > limit).then(|| data.chunks(limit).chain(std::iter::once(&[][..])))
+}
+
+fn answer_requests(display: Display, window: u64, contents: &Contents) -> Result<(), String> {
+    let clipboard = x11_safe::intern_atom(display, "CLIPBOARD");
+    let targets = x11_safe::intern_atom(display, "TARGETS");
+    let image_png = x11_safe::intern_atom(display, "image/png");
+    let text_targets = [
+        x11_safe::intern_atom(display, "UTF8_STRING"),
+        x11_safe::intern_atom(display, "text/plain;charset=utf-8"),
+    ];
+    let limit = x11_safe::max_property_bytes(display);
+
+    let mut offered = vec![targets];
+    if contents.png.is_some() {
+        offered.push(image_png);
+    }
+    if contents.text.is_some() {
+        offered.extend(text_targets);
+    }
+
+    x11_safe::set_selection_owner(display, clipboard, window).map_err(|e| e.to_string())?;
+
+    let mut transfers: Vec<Incr> = Vec::new();
+    loop {
+        match x11_safe::next_event(display) {
+            Event::SelectionRequest(request) => {
+                // obsolete clients leave property as None and expect the target to be used
+                let property = if request.property == 0 {
+                    request.target
+                } else {
+                    request.property
+                };
+
+                let data = match (contents.png, contents.text) {
+                    (Some(png), _) if request.target == image_png => Some(png),
+                    (_, Some(text)) if text_targets.contains(&request.target) => {
+                        Some(text.as_bytes())
+                    }
+                    _ => None,
+                };
+
+                let served = if request.target == targets {
+                    x11_safe::change_property_atoms(display, request.requestor, property, &offered);
+                    property
+                } else if let Some(data) = data {
+                    if let Some(pieces) = incr_pieces(data, limit) {
+                        // the deletions only come through once we're listening
+                        // for them, so this has to happen before the notify
+                        x11_safe::select_input(display, request.requestor, PropertyChangeMask);
+                        x11_safe::change_property_incr(
+                            display,
+                            request.requestor,
+                            property,
+                            data.len(),
+                        );
+                        transfers.push(Incr {
+                            requestor: request.requestor,
+                            property,
+                            target: request.target,
+                            pieces,
+                        });
+                    } else {
+                        x11_safe::change_property_bytes(
+                            display,
+                            request.requestor,
+                            property,
+                            request.target,
+                            data,
+                        );
+                    }
+                    property
+                } else {
+                    0
+                };
+
+                x11_safe::send_selection_notify(display, &request, served);
+            }
+            Event::PropertyDeleted { window, atom } => {
+                let Some(index) = transfers
+                    .iter()
+                    .position(|incr| incr.requestor == window && incr.property == atom)
+                else {
+                    continue;
+                };
+
+                let incr = &mut transfers[index];
+                let piece = incr.pieces.next().unwrap_or_default();
+                x11_safe::change_property_bytes(display, window, atom, incr.target, piece);
+                if piece.is_empty() {
+                    x11_safe::select_input(display, window, NoEventMask);
+                    transfers.swap_remove(index);
+                }
+            }
+            // a paste that's still coming through is cut short, the new owner
+            // has the clipboard now
+            Event::SelectionClear => return Ok(()),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn small_transfers_go_in_one_piece() {
+        assert!(incr_pieces(&[0; 100], 100).is_none());
+    }
+
+    #[test]
+    fn large_transfers_are_split_and_terminated() {
+        let data: Vec<u8> = (0..=255).cycle().take(1000).collect();
+        let pieces: Vec<&[u8]> = incr_pieces(&data, 300).unwrap().collect();
+
+        let sizes: Vec<usize> = pieces.iter().map(|piece| piece.len()).collect();
+        assert_eq!(sizes, [300, 300, 300, 100, 0]);
+        assert_eq!(pieces.concat(), data);
+    }
+}
@@ -0,0 +1,241 @@
+use crate::args::Args;
+use crate::MAX_LINE_WIDTH;
+use std::path::PathBuf;
+
+// defaults for a handful of flags, read from $XDG_CONFIG_HOME/sleek/config.toml
+// before the command line so flags always win. only the flat part of TOML is
+// understood: key = value lines with strings, integers and booleans, e.g.
+//
+//   line_colour = "ff8800"
+//   line_width = 3
+//   template = "sleek-%Y-%m-%d:%H:%M:%S.jpg"
+//   output_dir = "~/Pictures"
+//   quality = 85
+//   clipboard = true
+//   current_monitor = true
+//
+// the output format follows the template's extension like it does on the
+// command line. a config that's there but can't be used is an error, left for
+// the caller to report so --help and --version still work with a broken one
+pub fn apply(args: &mut Args) -> Result<(), String> {
+    let Some(path) = path() else {
+        return Ok(());
+    };
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        // no config is the normal case
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(format!("{}: {e}", path.display())),
+    };
+
+    apply_contents(args, &contents).map_err(|e| format!("{}:{e}", path.display()))
+}
+
+// stops at the first bad line, the error starts with its number
+fn apply_contents(args: &mut Args, contents: &str) -> Result<(), String> {
+    for (number, line) in contents.lines().enumerate() {
+        apply_line(args, line).map_err(|e| format!("{}: {e}", number + 1))?;
+    }
+    Ok(())
+}
+
+fn path() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("sleek").join("config.toml"))
+}
+
+enum Value {
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+}
+
+impl Value {
+    fn kind(&self) -> &'static str {
+        match self {
+            Value::String(_) => "a string",
+            Value::Integer(_) => "an integer",
+            Value::Boolean(_) => "a boolean",
+        }
+    }
+}
+
+fn apply_line(args: &mut Args, line: &str) -> Result<(), String> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(());
+    }
+    if line.starts_with('[') {
+        return Err("tables aren't supported, keys go at the top level".to_owned());
+    }
+
+    let (key, value) = line
+        .split_once('=')
+        .ok_or_else(|| format!("expected key = value, found '{line}'"))?;
+    let key = key.trim();
+    let value = parse_value(value.trim())?;
+
+    let wrong_type = |expected: &str| format!("{key} should be {expected}, not {}", value.kind());
+    match (key, &value) {
+        ("line_colour", Value::String(colour)) => {
+            args.line_colour = colour
+                .parse()
+                .map_err(|e| format!("invalid line_colour '{colour}': {e}"))?;
+        }
+        ("line_width", Value::Integer(width)) => {
+            if !(1..=MAX_LINE_WIDTH as i64).contains(width) {
+                return Err(format!("line_width must be between 1 and {MAX_LINE_WIDTH}"));
+            }
+            args.line_width = Some(*width as i32);
+        }
+        ("template", Value::String(template)) => args.template = Some(template.clone()),
+        ("output_dir", Value::String(directory)) => args.output_dir = Some(directory.clone()),
+        ("quality", Value::Integer(quality)) => {
+            if !(1..=100).contains(quality) {
+                return Err("quality must be between 1 and 100".to_owned());
+            }
+            args.quality = *quality as u8;
+        }
+        ("clipboard", Value::Boolean(clipboard)) => args.clipboard = *clipboard,
+        ("current_monitor", Value::Boolean(current)) => args.current_monitor = *current,
+        ("line_colour" | "template" | "output_dir", _) => return Err(wrong_type("a string")),
+        ("line_width" | "quality", _) => return Err(wrong_type("an integer")),
+        ("clipboard" | "current_monitor", _) => return Err(wrong_type("a boolean")),
+        _ => return Err(format!("unknown key '{key}'")),
+    }
+    Ok(())
+}
+
+fn parse_value(value: &str) -> Result<Value, String> {
+    if let Some(rest) = value.strip_prefix('"') {
+        let mut string = String::new();
+        let mut chars = rest.chars();
+        loop {
+            match chars.next() {
+                Some('"') => break,
+                Some('\\') => string.push(match chars.next() {
+                    Some('n') => '\n',
+                    Some('t') => '\t',
+                    Some(c @ ('"' | '\\')) => c,
+                    Some(c) => return Err(format!("unsupported escape \\{c}")),
+                    None => return Err("unterminated string".to_owned()),
+                }),
+                Some(c) => string.push(c),
+                None => return Err("unterminated string".to_owned()),
+            }
+        }
+        trailing(chars.as_str())?;
+        return Ok(Value::String(string));
+    }
+    if let Some(rest) = value.strip_prefix('\'') {
+        let (string, rest) = rest
+            .split_once('\'')
+            .ok_or_else(|| "unterminated string".to_owned())?;
+        trailing(rest)?;
+        return Ok(Value::String(string.to_owned()));
+    }
+
+    let bare = value.split('#').next().unwrap_or_default().trim();
+    match bare {
+        "true" => Ok(Value::Boolean(true)),
+        "false" => Ok(Value::Boolean(false)),
+        "" => Err("missing value".to_owned()),
+        _ => bare
+            .replace('_', "")
+            .parse()
+            .map(Value::Integer)
+            .map_err(|_| format!("invalid value '{bare}'")),
+    }
+}
+
+// only a comment may follow a value
+fn trailing(rest: &str) -> Result<(), String> {
+    let rest = rest.trim();
+    if rest.is_empty() || rest.starts_with('#') {
+        Ok(())
+    } else {
+        Err(format!("unexpected '{rest}' after value"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(contents: &str) -> Result<Args, String> {
+        let mut args = Args::default();
+        apply_contents(&mut args, contents).map(|()| args)
+    }
+
+    #[test]
+    fn skips_comments_and_blank_lines() {
+        let args = parse("# a comment\n\n  # indented\nquality = 50 # trailing\n").unwrap();
+        assert_eq!(args.quality, 50);
+    }
+
+    #[test]
+    fn hashes_in_strings_are_not_comments() {
+        let args = parse("template = \"shot#%Y\" # the year\noutput_dir = '~/a#b'").unwrap();
+        assert_eq!(args.template.as_deref(), Some("shot#%Y"));
+        assert_eq!(args.output_dir.as_deref(), Some("~/a#b"));
+    }
+
+    #[test]
+    fn strings_handle_escapes() {
+        let args = parse(r#"template = "a\"b\\c""#).unwrap();
+        assert_eq!(args.template.as_deref(), Some(r#"a"b\c"#));
+        assert!(parse(r#"template = "a\qb""#).is_err());
+        assert!(parse(r#"template = "open"#).is_err());
+    }
+
+    #[test]
+    fn integers_booleans_and_strings() {
+        let args = parse("line_width = 1_0\nclipboard = true\nline_colour = \"ff8800\"").unwrap();
+        assert_eq!(args.line_width, Some(10));
+        assert!(args.clipboard);
+        assert_eq!(
+            (args.line_colour.r, args.line_colour.g, args.line_colour.b),
+            (0xff, 0x88, 0x00)
+        );
+    }
+
+    #[test]
+    fn wrong_types_say_what_was_expected() {
+        assert_eq!(
+            parse("quality = \"85\"").err().unwrap(),
+            "1: quality should be an integer, not a string"
+        );
+        assert_eq!(
+            parse("template = 5").err().unwrap(),
+            "1: template should be a string, not an integer"
+        );
+        assert_eq!(
+            parse("clipboard = 1").err().unwrap(),
+            "1: clipboard should be a boolean, not an integer"
+        );
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        assert_eq!(
+            parse("colour = \"ff0000\"").err().unwrap(),
+            "1: unknown key 'colour'"
+        );
+    }
+
+    #[test]
+    fn errors_give_the_line_number() {
+        let e = parse("# fine\nquality = 85\n\nquality = 500\n")
+            .err()
+            .unwrap();
+        assert_eq!(e, "4: quality must be between 1 and 100");
+        assert!(parse("clipboard = true\n[table]")
+            .err()
+            .unwrap()
+            .starts_with("2: "));
+        assert!(parse("\n\nnot a pair").err().unwrap().starts_with("3: "));
+    }
+}
@@ -0,0 +1,201 @@
+use crate::args::Args;
+use crate::error::SleekError;
+use crate::x11_safe::{self, Display, Event, Font, Gc};
+use crate::{ScreenData, RGB, SETTLE_TIME};
+use std::time::{Duration, Instant};
+use x11::xlib::ExposureMask;
+
+const PADDING: i32 = 16;
+
+// --delay as a big number in the middle of the screen. everything around it
+// stays usable so menus can be opened in the meantime, only Escape is grabbed
+// so the countdown can be called off. false when it was
+pub fn run(args: &Args, seconds: u64) -> Result<bool, String> {
+    let connection = x11_safe::open_display().map_err(|e| e.to_string())?;
+    let display = connection.display();
+    count_down(display, args, seconds)
+}
+
+fn count_down(display: Display, args: &Args, seconds: u64) -> Result<bool, String> {
+    let screen_number = x11_safe::default_screen(display);
+    let root = x11_safe::root_window(display, screen_number);
+    let depth = x11_safe::default_depth(display, screen_number) as u32;
+    let (screen_width, screen_height) = x11_safe::display_size(display, screen_number);
+
+    let font = Font::load(display, "fixed").map_err(|e| e.to_string())?;
+    let widest = seconds.to_string();
+    let (text_width, text_height) = (
+        font.text_width(&widest).max(1),
+        font.ascent() + font.descent(),
+    );
+
+    // the digits are drawn in the small fixed font and blown up a block per
+    // pixel, so there's no need for a large font to be installed. a quarter
+    // of the screen's height keeps it readable on any size of display
+    let block = (screen_height / 4 / text_height).max(1);
+    let (width, height) = (
+        text_width * block + 2 * PADDING,
+        text_height * block + 2 * PADDING,
+    );
+
+    // picked the same way as the overlay's, so the colour packs the same way
+    let visual = crate::pick_visual(display, screen_number, depth)
+        .ok_or_else(|| SleekError::NoUsableVisual.to_string())?;
+    let layout = ScreenData::new(
+        0,
+        0,
+        depth,
+        visual.red_mask as u32,
+        visual.green_mask as u32,
+        visual.blue_mask as u32,
+    );
+
+    let window = x11_safe::create_popup(
+        display,
+        root,
+        (screen_width - width) / 2,
+        (screen_height - height) / 2,
+        width as u32,
+        height as u32,
+        0,
+    )
+    .map_err(|e| e.to_string())?;
+    let scratch = Scratch {
+        pixmap: x11_safe::create_pixmap(
+            display,
+            window,
+            text_width as u32,
+            text_height as u32,
+            depth,
+        )
+        .map_err(|e| e.to_string())?,
+        gc: x11_safe::create_default_gc(display, window).map_err(|e| e.to_string())?,
+        width: text_width,
+        height: text_height,
+        block,
+        colour: layout.encode(args.line_colour),
+        // black, unless that's what the digits are
+        background: match layout.encode(args.line_colour) {
+            0 => layout.encode(RGB::new(255, 255, 255)),
+            _ => 0,
+        },
+    };
+    font.set_on(display, scratch.gc);
+
+    x11_safe::select_input(display, window, ExposureMask);
+    x11_safe::map_raised(display, window);
+
+    let escape = x11_safe::keysym_to_keycode(display, "Escape").ok();
+    if let Some(keycode) = escape {
+        x11_safe::grab_key(display, keycode, root);
+    }
+    x11_safe::sync(display);
+
+    let mut finished = true;
+    'count: for remaining in (1..=seconds).rev() {
+        let text = remaining.to_string();
+        scratch.draw(display, window, &font, &text, width, height);
+
+        let next = Instant::now() + Duration::from_secs(1);
+        while let Some(left) = next.checked_duration_since(Instant::now()) {
+            x11_safe::wait_for_event(display, Some(left));
+            while x11_safe::pending(display) > 0 {
+                match x11_safe::next_event(display) {
+                    Event::KeyPress { keycode, .. } if Some(keycode as u8) == escape => {
+                        finished = false;
+                        break 'count;
+                    }
+                    // the first expose comes once the window is actually up
+                    _ => scratch.draw(display, window, &font, &text, width, height),
+                }
+            }
+        }
+    }
+
+    if let Some(keycode) = escape {
+        x11_safe::ungrab_key(display, keycode, root);
+    }
+    x11_safe::free_gc(display, scratch.gc);
+    x11_safe::free_pixmap(display, scratch.pixmap);
+    x11_safe::destroy_window(display, window);
+    drop(font);
+    x11_safe::sync(display);
+
+    // the snapshot comes next, whatever was under the number has to be back by then
+    std::thread::sleep(SETTLE_TIME);
+
+    Ok(finished)
+}
+
+// the text is rendered here at its real size and read back pixel by pixel
+struct Scratch {
+    pixmap: u64,
+    gc: Gc,
+    width: i32,
+    height: i32,
+    block: i32,
+    colour: u64,
+    background: u64,
+}
+
+impl Scratch {
+    fn draw(
+        &self,
+        display: Display,
+        window: u64,
+        font: &Font,
+        text: &str,
+        window_width: i32,
+        window_height: i32,
+    ) {
+        let fill = |drawable: u64, x: i32, y: i32, width: i32, height: i32| {
+            x11_safe::fill_rectangle(
+                display,
+                drawable,
+                self.gc,
+                x,
+                y,
+                width as u32,
+                height as u32,
+            )
+        };
+
+        x11_safe::set_foreground(display, self.gc, 0);
+        fill(self.pixmap, 0, 0, self.width, self.height);
+        x11_safe::set_foreground(display, self.gc, self.background);
+        fill(window, 0, 0, window_width, window_height);
+
+        // lit pixels are told apart from the cleared 0 background, so the
+        // scratch text is all ones whatever colour the blocks end up
+        x11_safe::set_foreground(display, self.gc, !0);
+        // fewer digits than the first number are centred in the same space
+        let offset = (self.width - font.text_width(text)) / 2;
+        x11_safe::draw_string(display, self.pixmap, self.gc, offset, font.ascent(), text);
+        x11_safe::set_foreground(display, self.gc, self.colour);
+
+        let Ok(image) = x11_safe::get_image(
+            display,
+            self.pixmap,
+            0,
+            0,
+            self.width as u32,
+            self.height as u32,
+        ) else {
+            return;
+        };
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if image.pixel(x, y) != 0 {
+                    fill(
+                        window,
+                        PADDING + x * self.block,
+                        PADDING + y * self.block,
+                        self.block,
+                        self.block,
+                    );
+                }
+            }
+        }
+        x11_safe::sync(display);
+    }
+}
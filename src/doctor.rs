@@ -0,0 +1,81 @@
+use crate::{monitor, x11_safe};
+use x11::xlib::{DirectColor, GrayScale, PseudoColor, StaticColor, StaticGray, TrueColor};
+
+// names as the server reports them to XQueryExtension
+const EXTENSIONS: [&str; 7] = [
+    "XFIXES",
+    "RANDR",
+    "XINERAMA",
+    "MIT-SHM",
+    "DAMAGE",
+    "Composite",
+    "RENDER",
+];
+
+// everything that is useful in a bug report, every probe just reports what it
+// finds so a missing extension never stops the rest of the report
+pub fn report() -> Result<(), String> {
+    let connection = x11_safe::open_display().map_err(|e| format!("display: {e}"))?;
+    let display = connection.display();
+    println!("display: connected");
+    if let Some(wayland) = std::env::var_os("WAYLAND_DISPLAY") {
+        println!(
+            "wayland: {} (captures go through XWayland)",
+            wayland.to_string_lossy()
+        );
+    }
+
+    println!("extensions:");
+    for extension in EXTENSIONS {
+        let available = if x11_safe::query_extension(display, extension) {
+            "available"
+        } else {
+            "unavailable"
+        };
+        println!("  {extension}: {available}");
+    }
+
+    let screen = x11_safe::default_screen(display);
+    let root = x11_safe::root_window(display, screen);
+
+    let compositor = match x11_safe::selection_owner(display, &format!("_NET_WM_CM_S{screen}")) {
+        Some(owner) => {
+            x11_safe::fetch_name(display, owner).unwrap_or_else(|| format!("{owner:#x}"))
+        }
+        None => "none".to_owned(),
+    };
+    println!("compositor: {compositor}");
+
+    println!(
+        "visual: depth {} {}",
+        x11_safe::default_depth(display, screen),
+        visual_class_name(x11_safe::default_visual_class(display, screen))
+    );
+
+    let monitors = monitor::monitors(display, root);
+    if monitors.is_empty() {
+        println!("monitors: none reported by XRandR");
+    } else {
+        println!("monitors:");
+        for (i, monitor) in monitors.iter().enumerate() {
+            println!(
+                "  {i}: {}x{}+{}+{} rotation {}",
+                monitor.width, monitor.height, monitor.x, monitor.y, monitor.rotation
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn visual_class_name(class: i32) -> &'static str {
+    match class {
+        c if c == TrueColor => "TrueColor",
+        c if c == DirectColor => "DirectColor",
+        c if c == PseudoColor => "PseudoColor",
+        c if c == StaticColor => "StaticColor",
+        c if c == GrayScale => "GrayScale",
+        c if c == StaticGray => "StaticGray",
+        _ => "unknown",
+    }
+}
@@ -0,0 +1,52 @@
+use crate::x11_safe;
+
+#[derive(Debug)]
+pub enum SleekError {
+    X(x11_safe::Error),
+    DisplayOpenFailed,
+    NoScreen(i32),
+    NoMonitor(usize),
+    NoUsableVisual,
+    Write {
+        path: String,
+        source: Box<dyn std::error::Error>,
+    },
+    Shm(std::io::Error),
+    Capture(String),
+    Template(String, String),
+}
+
+impl std::fmt::Display for SleekError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::X(e) => write!(f, "{e}"),
+            Self::DisplayOpenFailed => write!(f, "could not open the X display, is DISPLAY set?"),
+            Self::NoScreen(n) => write!(f, "X screen {n} does not exist, see --list-screens"),
+            Self::NoMonitor(n) => write!(f, "monitor {n} does not exist, see --doctor"),
+            Self::NoUsableVisual => {
+                write!(f, "no TrueColor or DirectColor visual at the root's depth")
+            }
+            Self::Write { path, source } => write!(f, "could not write {path}: {source}"),
+            Self::Shm(e) => write!(f, "--shm-name: {e}"),
+            Self::Capture(e) => write!(f, "could not capture: {e}"),
+            Self::Template(template, e) => write!(f, "template '{template}': {e}"),
+        }
+    }
+}
+
+impl std::error::Error for SleekError {}
+
+impl From<x11_safe::Error> for SleekError {
+    fn from(e: x11_safe::Error) -> Self {
+        Self::X(e)
+    }
+}
+
+impl SleekError {
+    pub fn write(path: &str, source: impl Into<Box<dyn std::error::Error>>) -> Self {
+        Self::Write {
+            path: path.to_owned(),
+            source: source.into(),
+        }
+    }
+}
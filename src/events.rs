@@ -0,0 +1,79 @@
+use crate::{Point, Rect};
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+// --emit-events, a line on stdout whenever the pointer or selection changes so
+// another process can follow along. coordinates are on the root like
+// --print-geometry. nothing is printed when it's off
+pub struct Stream {
+    enabled: bool,
+    origin: Point,
+    interval: Duration,
+    last_sent: Instant,
+    pointer: Point,
+    selection: Option<Rect>,
+}
+
+impl Stream {
+    pub fn new(enabled: bool, origin: Point, interval: Duration) -> Self {
+        Self {
+            enabled,
+            origin,
+            interval,
+            last_sent: Instant::now(),
+            // the overlay's own placeholder until the first motion event
+            pointer: Point::new(0, 0),
+            selection: None,
+        }
+    }
+
+    // called on every pass through the event loop, at most once a frame is
+    // sent and only for what moved since the last one
+    pub fn update(&mut self, pointer: Point, selection: Option<Rect>) {
+        if !self.enabled || self.last_sent.elapsed() < self.interval {
+            return;
+        }
+        self.last_sent = Instant::now();
+
+        if self.pointer != pointer {
+            self.pointer = pointer;
+            let Point { x, y } = self.to_root(pointer);
+            emit(&format!("MOVE {x} {y}"));
+        }
+        // a cleared selection isn't announced, but drawing the same one again is
+        if selection != self.selection {
+            self.selection = selection;
+            if let Some(rect) = selection {
+                let Point { x, y } = self.to_root(Point::new(rect.x, rect.y));
+                emit(&format!("SELECT {x} {y} {} {}", rect.width, rect.height));
+            }
+        }
+    }
+
+    // how long until update has something held back to send, None when it's
+    // caught up
+    pub fn due(&self, pointer: Point, selection: Option<Rect>) -> Option<Duration> {
+        (self.enabled && (pointer != self.pointer || selection != self.selection))
+            .then(|| self.interval.saturating_sub(self.last_sent.elapsed()))
+    }
+
+    pub fn commit(&mut self, rect: Rect) {
+        if !self.enabled {
+            return;
+        }
+        let Point { x, y } = self.to_root(Point::new(rect.x, rect.y));
+        emit(&format!("COMMIT {x} {y} {} {}", rect.width, rect.height));
+        self.selection = None;
+    }
+
+    fn to_root(&self, point: Point) -> Point {
+        Point::new(point.x + self.origin.x, point.y + self.origin.y)
+    }
+}
+
+// flushed straight away, stdout is a pipe whenever anything is listening
+fn emit(line: &str) {
+    let mut stdout = std::io::stdout().lock();
+    let _ = writeln!(stdout, "{line}");
+    let _ = stdout.flush();
+}
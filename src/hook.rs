@@ -0,0 +1,93 @@
+use crate::args::Args;
+use crate::log;
+use std::process::Command;
+
+// runs --exec for a file that was just saved. the command is split into
+// arguments here rather than handed to a shell, so a path full of spaces or
+// quotes stays one argument. --exec-shell runs it with sh -c instead, with
+// the path in $1 so it still never gets parsed by the shell
+pub fn run(args: &Args, command: &str, path: &str) {
+    let mut child = if args.exec_shell {
+        let mut child = Command::new("sh");
+        child
+            .arg("-c")
+            .arg(command.replace("{}", "\"$1\""))
+            .arg("sh")
+            .arg(path);
+        child
+    } else {
+        let words = match split(command) {
+            Ok(words) if !words.is_empty() => words,
+            Ok(_) => return,
+            Err(e) => {
+                log::warn(&format!("--exec: {e}"));
+                return;
+            }
+        };
+        let mut words = words.into_iter().map(|word| word.replace("{}", path));
+        let mut child = Command::new(words.next().unwrap());
+        child.args(words);
+        child
+    };
+
+    let result = child.spawn().and_then(|mut child| {
+        if args.exec_wait {
+            child.wait().map(Some)
+        } else {
+            Ok(None)
+        }
+    });
+    match result {
+        Ok(Some(status)) if !status.success() => {
+            log::warn(&format!("--exec: command exited with {status}"))
+        }
+        Ok(_) => {}
+        Err(e) => log::warn(&format!("--exec: {e}")),
+    }
+}
+
+// notify-send talks to org.freedesktop.Notifications for us, which saves
+// pulling in a D-Bus client. it's not waited on, and the image doubles as the icon
+pub fn notify(path: &str) {
+    let result = Command::new("notify-send")
+        .args(["--app-name=sleek", "--icon", path, "Screenshot saved"])
+        .arg(format!("Saved to {path}"))
+        .spawn();
+    if let Err(e) = result {
+        log::warn(&format!("--notify: notify-send: {e}"));
+    }
+}
+
+// whitespace separated words, '...' and "..." group words and \ escapes the
+// next character outside of single quotes
+fn split(command: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = command.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some(next) if next == c => break,
+                        Some('\\') if c == '"' => {
+                            word.push(chars.next().ok_or("trailing \\")?);
+                        }
+                        Some(next) => word.push(next),
+                        None => return Err(format!("unterminated {c}")),
+                    }
+                }
+            }
+            '\\' => word
+                .get_or_insert_with(String::new)
+                .push(chars.next().ok_or("trailing \\")?),
+            c if c.is_whitespace() => words.extend(word.take()),
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+
+    Ok(words)
+}
@@ -0,0 +1,47 @@
+use crate::x11_safe::{self, Event};
+use std::time::{Duration, Instant};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+// blocks until the key is pressed anywhere on the screen, after which sleek
+// carries on exactly as if it had just been launched
+pub fn wait_for_key(keysym: &str, timeout: Option<Duration>) -> Result<(), String> {
+    let connection = x11_safe::open_display().map_err(|e| e.to_string())?;
+    let display = connection.display();
+    let root = x11_safe::root_window(display, x11_safe::default_screen(display));
+
+    let keycode = match x11_safe::keysym_to_keycode(display, keysym) {
+        Ok(keycode) => keycode,
+        Err(_) => {
+            return Err(format!("unknown key '{keysym}'"));
+        }
+    };
+
+    x11_safe::grab_key(display, keycode, root);
+    x11_safe::sync(display);
+
+    let start = Instant::now();
+    let result = loop {
+        if timeout.is_some_and(|timeout| start.elapsed() >= timeout) {
+            break Err(format!("timed out waiting for '{keysym}'"));
+        }
+
+        if x11_safe::pending(display) == 0 {
+            std::thread::sleep(POLL_INTERVAL);
+            continue;
+        }
+
+        if let Event::KeyPress {
+            keycode: pressed, ..
+        } = x11_safe::next_event(display)
+        {
+            if pressed == keycode as u32 {
+                break Ok(());
+            }
+        }
+    };
+
+    x11_safe::ungrab_key(display, keycode, root);
+
+    result
+}
@@ -0,0 +1,321 @@
+use crate::annotate::TextMask;
+use crate::args::Args;
+use crate::{output, x11_safe, Point, Rect, RenderWindow, RGB};
+
+const TEXT_PADDING: i32 = 4;
+const CURSOR_OFFSET: i32 = 16;
+
+// centred just below the reference, falling back to above it and then to
+// inside its bottom edge when there isn't room on screen
+fn text_origin(render_window: &RenderWindow, reference: Rect, width: i32, height: i32) -> Point {
+    let screen_width = render_window.screen_data.width;
+    let screen_height = render_window.screen_data.height;
+
+    let x = (reference.x + (reference.width - width) / 2).clamp(0, (screen_width - width).max(0));
+
+    let below = reference.y + reference.height + TEXT_PADDING;
+    let above = reference.y - TEXT_PADDING - height;
+    let y = if below + height <= screen_height {
+        below
+    } else if above >= 0 {
+        above
+    } else {
+        reference.y + reference.height - TEXT_PADDING - height
+    };
+
+    Point::new(x, y)
+}
+
+// with --center-on-region the label is centred on the selection, otherwise
+// it trails the cursor
+pub fn draw_dimensions(
+    render_window: &mut RenderWindow,
+    args: &Args,
+    min: Point,
+    max: Point,
+    cursor: Point,
+) {
+    let text = format!("{}x{}", max.x - min.x, max.y - min.y);
+
+    if args.center_on_region {
+        let selection = Rect::new(min.x, min.y, max.x - min.x, max.y - min.y);
+        draw_text(render_window, &text, selection);
+        return;
+    }
+
+    let Some((width, height)) = label_size(render_window, &text) else {
+        return;
+    };
+    let origin = cursor_origin(render_window, cursor, width, height);
+    draw_label(render_window, &text, origin);
+}
+
+// below and to the right of the cursor, flipped to the other side on an axis
+// where it would run off the screen
+fn cursor_origin(render_window: &RenderWindow, cursor: Point, width: i32, height: i32) -> Point {
+    let offset = CURSOR_OFFSET;
+
+    let x = if cursor.x + offset + width > render_window.screen_data.width {
+        cursor.x - offset - width
+    } else {
+        cursor.x + offset
+    };
+    let y = if cursor.y + offset + height > render_window.screen_data.height {
+        cursor.y - offset - height
+    } else {
+        cursor.y + offset
+    };
+
+    Point::new(x.max(0), y.max(0))
+}
+
+// the colour under the pointer next to a swatch of it, for --pick
+pub fn draw_colour(render_window: &mut RenderWindow, colour: RGB, cursor: Point) {
+    let text = colour.to_string();
+    let Some((width, height)) = label_size(render_window, &text) else {
+        return;
+    };
+    let origin = cursor_origin(render_window, cursor, height + width, height);
+
+    let swatch = Rect::new(origin.x, origin.y, height, height);
+    x11_safe::set_foreground(
+        render_window.display,
+        render_window.graphics_context,
+        render_window.screen_data.encode(colour),
+    );
+    x11_safe::fill_rectangle(
+        render_window.display,
+        render_window.buffer,
+        render_window.graphics_context,
+        swatch.x,
+        swatch.y,
+        swatch.width as u32,
+        swatch.height as u32,
+    );
+    render_window.mark_drawn(swatch);
+
+    draw_label(
+        render_window,
+        &text,
+        Point::new(origin.x + height, origin.y),
+    );
+}
+
+fn draw_text(render_window: &mut RenderWindow, text: &str, reference: Rect) {
+    let Some((width, height)) = label_size(render_window, text) else {
+        return;
+    };
+    let origin = text_origin(render_window, reference, width, height);
+    draw_label(render_window, text, origin);
+}
+
+// what Enter is going to do, kept in the top left corner
+pub fn draw_status(render_window: &mut RenderWindow, args: &Args) {
+    let mode = if args.print_geometry.is_some() {
+        "geometry"
+    } else if args.monitor_region {
+        "watch"
+    } else if !args.resolutions.is_empty() {
+        "icons"
+    } else if args.selection_outline_only {
+        "outline"
+    } else {
+        "region"
+    };
+
+    let format = args
+        .template
+        .as_deref()
+        .and_then(output::Format::from_path)
+        .unwrap_or(output::Format::Png);
+    let destination = match args.template.as_deref() {
+        Some("-") => "stdout",
+        _ if args.clipboard => "clipboard",
+        _ => "file",
+    };
+
+    let status = format!("{} | {destination} | {mode}", format.name());
+    draw_label(
+        render_window,
+        &status,
+        Point::new(TEXT_PADDING, TEXT_PADDING),
+    );
+}
+
+// the text in the overlay's font, read back off a scratch pixmap so it can be
+// drawn into a saved image. None without a font
+pub fn text_mask(render_window: &RenderWindow, text: &str) -> Option<TextMask> {
+    let font = render_window.font.as_ref()?;
+    let (width, height) = (font.text_width(text).max(1), font.ascent() + font.descent());
+    let (display, gc) = (render_window.display, render_window.graphics_context);
+
+    let depth = x11_safe::get_geometry(display, render_window.window)
+        .ok()?
+        .depth;
+    let pixmap = x11_safe::create_pixmap(
+        display,
+        render_window.window,
+        width as u32,
+        height as u32,
+        depth,
+    )
+    .ok()?;
+    x11_safe::set_foreground(display, gc, 0);
+    x11_safe::fill_rectangle(display, pixmap, gc, 0, 0, width as u32, height as u32);
+    x11_safe::set_foreground(
+        display,
+        gc,
+        render_window.screen_data.encode(RGB::new(255, 255, 255)),
+    );
+    x11_safe::draw_string(display, pixmap, gc, 0, font.ascent(), text);
+    let image = x11_safe::get_image(display, pixmap, 0, 0, width as u32, height as u32);
+    x11_safe::free_pixmap(display, pixmap);
+    x11_safe::set_foreground(
+        display,
+        gc,
+        render_window.screen_data.encode(render_window.line_colour),
+    );
+
+    let image = image.ok()?;
+    let bits = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| image.pixel(x, y) != 0)
+        .collect();
+    Some(TextMask {
+        width,
+        height,
+        bits,
+    })
+}
+
+fn label_size(render_window: &RenderWindow, text: &str) -> Option<(i32, i32)> {
+    let font = render_window.font.as_ref()?;
+    Some((
+        font.text_width(text) + 2 * TEXT_PADDING,
+        font.ascent() + font.descent() + 2 * TEXT_PADDING,
+    ))
+}
+
+// thin guides splitting the selection into divisions x divisions cells, in
+// the line colour washed halfway to white so they sit behind the outline
+pub fn draw_grid(render_window: &mut RenderWindow, selection: Rect, divisions: i32) {
+    let RGB { r, g, b } = render_window.line_colour;
+    let wash = |channel: u8| ((channel as u16 + 255) / 2) as u8;
+    x11_safe::set_foreground(
+        render_window.display,
+        render_window.graphics_context,
+        render_window
+            .screen_data
+            .encode(RGB::new(wash(r), wash(g), wash(b))),
+    );
+
+    for i in 1..divisions {
+        let x = selection.x + selection.width * i / divisions;
+        let y = selection.y + selection.height * i / divisions;
+        for line in [
+            Rect::new(x, selection.y, 1, selection.height),
+            Rect::new(selection.x, y, selection.width, 1),
+        ] {
+            x11_safe::fill_rectangle(
+                render_window.display,
+                render_window.buffer,
+                render_window.graphics_context,
+                line.x,
+                line.y,
+                line.width as u32,
+                line.height as u32,
+            );
+            render_window.mark_drawn(line);
+        }
+    }
+
+    x11_safe::set_foreground(
+        render_window.display,
+        render_window.graphics_context,
+        render_window.screen_data.encode(render_window.line_colour),
+    );
+}
+
+// a one pixel line across the whole screen on each axis through the pointer.
+// only the two strips are marked, so the next frame restores just those
+pub fn draw_crosshair(render_window: &mut RenderWindow) {
+    let pointer = render_window.pointer;
+    for line in [
+        Rect::new(0, pointer.y, render_window.screen_data.width, 1),
+        Rect::new(pointer.x, 0, 1, render_window.screen_data.height),
+    ] {
+        x11_safe::fill_rectangle(
+            render_window.display,
+            render_window.buffer,
+            render_window.graphics_context,
+            line.x,
+            line.y,
+            line.width as u32,
+            line.height as u32,
+        );
+        render_window.mark_drawn(line);
+    }
+}
+
+// only outlined, the area itself is blurred once it's saved
+pub fn draw_redaction(render_window: &mut RenderWindow, rect: Rect) {
+    x11_safe::set_foreground(
+        render_window.display,
+        render_window.graphics_context,
+        render_window.screen_data.encode(render_window.line_colour),
+    );
+    for edge in [
+        Rect::new(rect.x, rect.y, rect.width, 1),
+        Rect::new(rect.x, rect.y + rect.height - 1, rect.width, 1),
+        Rect::new(rect.x, rect.y, 1, rect.height),
+        Rect::new(rect.x + rect.width - 1, rect.y, 1, rect.height),
+    ] {
+        x11_safe::fill_rectangle(
+            render_window.display,
+            render_window.buffer,
+            render_window.graphics_context,
+            edge.x,
+            edge.y,
+            edge.width as u32,
+            edge.height as u32,
+        );
+        render_window.mark_drawn(edge);
+    }
+}
+
+fn draw_label(render_window: &mut RenderWindow, text: &str, origin: Point) {
+    let Some((box_width, box_height)) = label_size(render_window, text) else {
+        return;
+    };
+    let ascent = render_window.font.as_ref().unwrap().ascent();
+
+    let display = render_window.display;
+    let buffer = render_window.buffer;
+    let gc = render_window.graphics_context;
+
+    render_window.mark_drawn(Rect::new(origin.x, origin.y, box_width, box_height));
+    x11_safe::set_foreground(display, gc, 0);
+    x11_safe::fill_rectangle(
+        display,
+        buffer,
+        gc,
+        origin.x,
+        origin.y,
+        box_width as u32,
+        box_height as u32,
+    );
+
+    x11_safe::set_foreground(
+        display,
+        gc,
+        render_window.screen_data.encode(render_window.line_colour),
+    );
+    x11_safe::draw_string(
+        display,
+        buffer,
+        gc,
+        origin.x + TEXT_PADDING,
+        origin.y + TEXT_PADDING + ascent,
+        text,
+    );
+}
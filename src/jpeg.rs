@@ -0,0 +1,629 @@
+use image::RgbImage;
+use std::io::Write;
+
+// --jpeg-subsampling and --jpeg-progressive. the image crate's encoder is
+// baseline 4:4:4 only, so anything else is encoded here. the tables are the
+// example ones from annex K of the standard, like every other encoder uses
+
+// 4:4:4 keeps full colour detail, which text and UI edges need. 4:2:2 halves
+// the chroma across and 4:2:0 down as well, smaller for photos
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Subsampling {
+    S444,
+    S422,
+    S420,
+}
+
+impl std::str::FromStr for Subsampling {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "444" => Ok(Self::S444),
+            "422" => Ok(Self::S422),
+            "420" => Ok(Self::S420),
+            _ => Err("expected 444, 422 or 420".to_owned()),
+        }
+    }
+}
+
+impl Subsampling {
+    // luma's sampling factors across and down, chroma is always 1x1
+    fn factors(self) -> (usize, usize) {
+        match self {
+            Self::S444 => (1, 1),
+            Self::S422 => (2, 1),
+            Self::S420 => (2, 2),
+        }
+    }
+}
+
+#[rustfmt::skip]
+const LUMA_QUANTISATION: [u8; 64] = [
+    16, 11, 10, 16,  24,  40,  51,  61,
+    12, 12, 14, 19,  26,  58,  60,  55,
+    14, 13, 16, 24,  40,  57,  69,  56,
+    14, 17, 22, 29,  51,  87,  80,  62,
+    18, 22, 37, 56,  68, 109, 103,  77,
+    24, 35, 55, 64,  81, 104, 113,  92,
+    49, 64, 78, 87, 103, 121, 120, 101,
+    72, 92, 95, 98, 112, 100, 103,  99,
+];
+
+#[rustfmt::skip]
+const CHROMA_QUANTISATION: [u8; 64] = [
+    17, 18, 24, 47, 99, 99, 99, 99,
+    18, 21, 26, 66, 99, 99, 99, 99,
+    24, 26, 56, 99, 99, 99, 99, 99,
+    47, 66, 99, 99, 99, 99, 99, 99,
+    99, 99, 99, 99, 99, 99, 99, 99,
+    99, 99, 99, 99, 99, 99, 99, 99,
+    99, 99, 99, 99, 99, 99, 99, 99,
+    99, 99, 99, 99, 99, 99, 99, 99,
+];
+
+// the natural (row major) index of each coefficient in zigzag order
+#[rustfmt::skip]
+const ZIGZAG: [usize; 64] = [
+     0,  1,  8, 16,  9,  2,  3, 10,
+    17, 24, 32, 25, 18, 11,  4,  5,
+    12, 19, 26, 33, 40, 48, 41, 34,
+    27, 20, 13,  6,  7, 14, 21, 28,
+    35, 42, 49, 56, 57, 50, 43, 36,
+    29, 22, 15, 23, 30, 37, 44, 51,
+    58, 59, 52, 45, 38, 31, 39, 46,
+    53, 60, 61, 54, 47, 55, 62, 63,
+];
+
+// Huffman tables as the number of codes of each length and the symbols
+const LUMA_DC: ([u8; 16], &[u8]) = (
+    [0, 1, 5, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0],
+    &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+);
+
+const CHROMA_DC: ([u8; 16], &[u8]) = (
+    [0, 3, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0],
+    &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+);
+
+#[rustfmt::skip]
+const LUMA_AC: ([u8; 16], &[u8]) = (
+    [0, 2, 1, 3, 3, 2, 4, 3, 5, 5, 4, 4, 0, 0, 1, 0x7d],
+    &[
+        0x01, 0x02, 0x03, 0x00, 0x04, 0x11, 0x05, 0x12, 0x21, 0x31, 0x41, 0x06, 0x13, 0x51, 0x61, 0x07,
+        0x22, 0x71, 0x14, 0x32, 0x81, 0x91, 0xa1, 0x08, 0x23, 0x42, 0xb1, 0xc1, 0x15, 0x52, 0xd1, 0xf0,
+        0x24, 0x33, 0x62, 0x72, 0x82, 0x09, 0x0a, 0x16, 0x17, 0x18, 0x19, 0x1a, 0x25, 0x26, 0x27, 0x28,
+        0x29, 0x2a, 0x34, 0x35, 0x36, 0x37, 0x38, 0x39, 0x3a, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48, 0x49,
+        0x4a, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5a, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68, 0x69,
+        0x6a, 0x73, 0x74, 0x75, 0x76, 0x77, 0x78, 0x79, 0x7a, 0x83, 0x84, 0x85, 0x86, 0x87, 0x88, 0x89,
+        0x8a, 0x92, 0x93, 0x94, 0x95, 0x96, 0x97, 0x98, 0x99, 0x9a, 0xa2, 0xa3, 0xa4, 0xa5, 0xa6, 0xa7,
+        0xa8, 0xa9, 0xaa, 0xb2, 0xb3, 0xb4, 0xb5, 0xb6, 0xb7, 0xb8, 0xb9, 0xba, 0xc2, 0xc3, 0xc4, 0xc5,
+        0xc6, 0xc7, 0xc8, 0xc9, 0xca, 0xd2, 0xd3, 0xd4, 0xd5, 0xd6, 0xd7, 0xd8, 0xd9, 0xda, 0xe1, 0xe2,
+        0xe3, 0xe4, 0xe5, 0xe6, 0xe7, 0xe8, 0xe9, 0xea, 0xf1, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8,
+        0xf9, 0xfa,
+    ],
+);
+
+#[rustfmt::skip]
+const CHROMA_AC: ([u8; 16], &[u8]) = (
+    [0, 2, 1, 2, 4, 4, 3, 4, 7, 5, 4, 4, 0, 1, 2, 0x77],
+    &[
+        0x00, 0x01, 0x02, 0x03, 0x11, 0x04, 0x05, 0x21, 0x31, 0x06, 0x12, 0x41, 0x51, 0x07, 0x61, 0x71,
+        0x13, 0x22, 0x32, 0x81, 0x08, 0x14, 0x42, 0x91, 0xa1, 0xb1, 0xc1, 0x09, 0x23, 0x33, 0x52, 0xf0,
+        0x15, 0x62, 0x72, 0xd1, 0x0a, 0x16, 0x24, 0x34, 0xe1, 0x25, 0xf1, 0x17, 0x18, 0x19, 0x1a, 0x26,
+        0x27, 0x28, 0x29, 0x2a, 0x35, 0x36, 0x37, 0x38, 0x39, 0x3a, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48,
+        0x49, 0x4a, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5a, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68,
+        0x69, 0x6a, 0x73, 0x74, 0x75, 0x76, 0x77, 0x78, 0x79, 0x7a, 0x82, 0x83, 0x84, 0x85, 0x86, 0x87,
+        0x88, 0x89, 0x8a, 0x92, 0x93, 0x94, 0x95, 0x96, 0x97, 0x98, 0x99, 0x9a, 0xa2, 0xa3, 0xa4, 0xa5,
+        0xa6, 0xa7, 0xa8, 0xa9, 0xaa, 0xb2, 0xb3, 0xb4, 0xb5, 0xb6, 0xb7, 0xb8, 0xb9, 0xba, 0xc2, 0xc3,
+        0xc4, 0xc5, 0xc6, 0xc7, 0xc8, 0xc9, 0xca, 0xd2, 0xd3, 0xd4, 0xd5, 0xd6, 0xd7, 0xd8, 0xd9, 0xda,
+        0xe2, 0xe3, 0xe4, 0xe5, 0xe6, 0xe7, 0xe8, 0xe9, 0xea, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8,
+        0xf9, 0xfa,
+    ],
+);
+
+// the progressive scans after the DC one, as (component, first, last). luma's
+// low frequencies come first so a partial download is already recognisable
+const PROGRESSIVE_SCANS: [(usize, usize, usize); 4] =
+    [(0, 1, 5), (1, 1, 63), (2, 1, 63), (0, 6, 63)];
+
+// a code and its length for each symbol
+type Codes = [(u16, u8); 256];
+
+struct Tables {
+    dc: [Codes; 2],
+    ac: [Codes; 2],
+}
+
+struct Component {
+    id: u8,
+    // sampling factors
+    h: usize,
+    v: usize,
+    // 0 for luma, 1 for chroma. picks both the quantisation and Huffman tables
+    table: usize,
+    // quantised, in zigzag order, row by row over the plane padded out to
+    // whole MCUs
+    blocks: Vec<[i32; 64]>,
+    across: usize,
+    // how many blocks a scan of just this component covers, only what the
+    // image itself needs rather than the padding
+    needed: (usize, usize),
+}
+
+pub fn encode<W: Write>(
+    mut writer: W,
+    image: &RgbImage,
+    quality: u8,
+    subsampling: Subsampling,
+    progressive: bool,
+) -> std::io::Result<()> {
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    if width == 0 || height == 0 || width > 0xffff || height > 0xffff {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("JPEG can't be {width}x{height}"),
+        ));
+    }
+
+    let quantisation = [
+        scale_quantisation(&LUMA_QUANTISATION, quality),
+        scale_quantisation(&CHROMA_QUANTISATION, quality),
+    ];
+    let components = components(image, subsampling, &quantisation);
+    let tables = Tables {
+        dc: [codes(&LUMA_DC), codes(&CHROMA_DC)],
+        ac: [codes(&LUMA_AC), codes(&CHROMA_AC)],
+    };
+
+    let mut out = Vec::new();
+    out.extend([0xff, 0xd8]);
+    // JFIF 1.01, no density and no thumbnail
+    segment(
+        &mut out,
+        0xe0,
+        &[b'J', b'F', b'I', b'F', 0, 1, 1, 0, 0, 1, 0, 1, 0, 0],
+    );
+
+    for (id, table) in quantisation.iter().enumerate() {
+        let mut body = vec![id as u8];
+        body.extend(ZIGZAG.iter().map(|&natural| table[natural] as u8));
+        segment(&mut out, 0xdb, &body);
+    }
+
+    let mut frame = vec![8];
+    frame.extend((height as u16).to_be_bytes());
+    frame.extend((width as u16).to_be_bytes());
+    frame.push(components.len() as u8);
+    for component in &components {
+        frame.extend([
+            component.id,
+            ((component.h << 4) | component.v) as u8,
+            component.table as u8,
+        ]);
+    }
+    segment(&mut out, if progressive { 0xc2 } else { 0xc0 }, &frame);
+
+    let mut huffman = Vec::new();
+    for (class, tables) in [(0, [&LUMA_DC, &CHROMA_DC]), (1, [&LUMA_AC, &CHROMA_AC])] {
+        for (id, (lengths, symbols)) in tables.into_iter().enumerate() {
+            huffman.push((class << 4) | id as u8);
+            huffman.extend(lengths);
+            huffman.extend(*symbols);
+        }
+    }
+    segment(&mut out, 0xc4, &huffman);
+
+    let all: Vec<&Component> = components.iter().collect();
+    if progressive {
+        scan(&mut out, &all, 0, 0, &tables);
+        for (component, first, last) in PROGRESSIVE_SCANS {
+            scan(&mut out, &[&components[component]], first, last, &tables);
+        }
+    } else {
+        scan(&mut out, &all, 0, 63, &tables);
+    }
+
+    out.extend([0xff, 0xd9]);
+    writer.write_all(&out)
+}
+
+// libjpeg's scaling, 50 is the tables as they are
+fn scale_quantisation(base: &[u8; 64], quality: u8) -> [u32; 64] {
+    let quality = quality.clamp(1, 100) as u32;
+    let scale = if quality < 50 {
+        5000 / quality
+    } else {
+        200 - quality * 2
+    };
+    base.map(|value| ((value as u32 * scale + 50) / 100).clamp(1, 255))
+}
+
+fn codes((lengths, symbols): &([u8; 16], &[u8])) -> Codes {
+    let mut codes = [(0, 0); 256];
+    let mut symbols = symbols.iter();
+    let mut code = 0u16;
+    for (length, count) in lengths.iter().enumerate() {
+        for symbol in symbols.by_ref().take(*count as usize) {
+            codes[*symbol as usize] = (code, length as u8 + 1);
+            code += 1;
+        }
+        code <<= 1;
+    }
+    codes
+}
+
+fn segment(out: &mut Vec<u8>, marker: u8, body: &[u8]) {
+    out.extend([0xff, marker]);
+    out.extend((body.len() as u16 + 2).to_be_bytes());
+    out.extend(body);
+}
+
+// Y, Cb and Cr with the edge pixels repeated out to whole MCUs, chroma
+// averaged down when it's subsampled
+fn components(
+    image: &RgbImage,
+    subsampling: Subsampling,
+    quantisation: &[[u32; 64]; 2],
+) -> Vec<Component> {
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let (h, v) = subsampling.factors();
+    let padded_width = width.div_ceil(8 * h) * 8 * h;
+    let padded_height = height.div_ceil(8 * v) * 8 * v;
+
+    let mut planes = [
+        vec![0.0; padded_width * padded_height],
+        vec![0.0; padded_width * padded_height],
+        vec![0.0; padded_width * padded_height],
+    ];
+    for y in 0..padded_height {
+        for x in 0..padded_width {
+            let [r, g, b] = image
+                .get_pixel(x.min(width - 1) as u32, y.min(height - 1) as u32)
+                .0
+                .map(|c| c as f32);
+            let at = y * padded_width + x;
+            planes[0][at] = 0.299 * r + 0.587 * g + 0.114 * b;
+            planes[1][at] = -0.168_736 * r - 0.331_264 * g + 0.5 * b + 128.0;
+            planes[2][at] = 0.5 * r - 0.418_688 * g - 0.081_312 * b + 128.0;
+        }
+    }
+
+    let [luma, blue, red] = planes;
+    let chroma = |plane: Vec<f32>| downsample(&plane, padded_width, padded_height, h, v);
+    [
+        (1, (h, v), 0, luma, padded_width, width, height),
+        (
+            2,
+            (1, 1),
+            1,
+            chroma(blue),
+            padded_width / h,
+            width.div_ceil(h),
+            height.div_ceil(v),
+        ),
+        (
+            3,
+            (1, 1),
+            1,
+            chroma(red),
+            padded_width / h,
+            width.div_ceil(h),
+            height.div_ceil(v),
+        ),
+    ]
+    .into_iter()
+    .map(
+        |(id, (h, v), table, plane, plane_width, width, height)| Component {
+            id,
+            h,
+            v,
+            table,
+            blocks: blocks(&plane, plane_width, &quantisation[table]),
+            across: plane_width / 8,
+            needed: (width.div_ceil(8), height.div_ceil(8)),
+        },
+    )
+    .collect()
+}
+
+fn downsample(plane: &[f32], width: usize, height: usize, h: usize, v: usize) -> Vec<f32> {
+    if (h, v) == (1, 1) {
+        return plane.to_vec();
+    }
+    let (small_width, small_height) = (width / h, height / v);
+    let mut small = vec![0.0; small_width * small_height];
+    for y in 0..small_height {
+        for x in 0..small_width {
+            let mut sum = 0.0;
+            for dy in 0..v {
+                for dx in 0..h {
+                    sum += plane[(y * v + dy) * width + x * h + dx];
+                }
+            }
+            small[y * small_width + x] = sum / (h * v) as f32;
+        }
+    }
+    small
+}
+
+fn blocks(plane: &[f32], width: usize, quantisation: &[u32; 64]) -> Vec<[i32; 64]> {
+    let height = plane.len() / width;
+    let mut blocks = Vec::with_capacity((width / 8) * (height / 8));
+    for row in 0..height / 8 {
+        for column in 0..width / 8 {
+            let mut samples = [0.0; 64];
+            for y in 0..8 {
+                for x in 0..8 {
+                    samples[y * 8 + x] = plane[(row * 8 + y) * width + column * 8 + x] - 128.0;
+                }
+            }
+            let coefficients = dct(&samples);
+            blocks.push(std::array::from_fn(|i| {
+                let natural = ZIGZAG[i];
+                (coefficients[natural] / quantisation[natural] as f32).round() as i32
+            }));
+        }
+    }
+    blocks
+}
+
+// the orthonormal 2D DCT-II, which is the standard's FDCT
+fn dct(samples: &[f32; 64]) -> [f32; 64] {
+    let basis: [[f32; 8]; 8] = std::array::from_fn(|u| {
+        let scale = if u == 0 { (0.125f32).sqrt() } else { 0.5 };
+        std::array::from_fn(|x| {
+            scale * ((2 * x + 1) as f32 * u as f32 * std::f32::consts::PI / 16.0).cos()
+        })
+    });
+
+    let mut rows = [0.0; 64];
+    for y in 0..8 {
+        for u in 0..8 {
+            rows[y * 8 + u] = (0..8).map(|x| basis[u][x] * samples[y * 8 + x]).sum();
+        }
+    }
+    let mut out = [0.0; 64];
+    for v in 0..8 {
+        for u in 0..8 {
+            out[v * 8 + u] = (0..8).map(|y| basis[v][y] * rows[y * 8 + u]).sum();
+        }
+    }
+    out
+}
+
+// a scan over coefficients first..=last of the components. several
+// components are interleaved MCU by MCU, a single one goes block by block
+// over just the blocks the image needs
+fn scan(out: &mut Vec<u8>, components: &[&Component], first: usize, last: usize, tables: &Tables) {
+    let mut header = vec![components.len() as u8];
+    for component in components {
+        header.extend([
+            component.id,
+            ((component.table << 4) | component.table) as u8,
+        ]);
+    }
+    // successive approximation isn't used, every coefficient goes in whole
+    header.extend([first as u8, last as u8, 0]);
+    segment(out, 0xda, &header);
+
+    let mut bits = BitWriter::new(out);
+    let mut previous = vec![0; components.len()];
+    let mut block = |bits: &mut BitWriter, index: usize, component: &Component, at: usize| {
+        encode_block(
+            bits,
+            &component.blocks[at],
+            first,
+            last,
+            &mut previous[index],
+            &tables.dc[component.table],
+            &tables.ac[component.table],
+        );
+    };
+
+    if let [component] = components {
+        let (across, down) = component.needed;
+        for row in 0..down {
+            for column in 0..across {
+                block(&mut bits, 0, component, row * component.across + column);
+            }
+        }
+    } else {
+        let luma = components[0];
+        let mcus_across = luma.across / luma.h;
+        let mcus_down = luma.blocks.len() / luma.across / luma.v;
+        for mcu_row in 0..mcus_down {
+            for mcu_column in 0..mcus_across {
+                for (index, component) in components.iter().enumerate() {
+                    for y in 0..component.v {
+                        for x in 0..component.h {
+                            let row = mcu_row * component.v + y;
+                            let column = mcu_column * component.h + x;
+                            block(&mut bits, index, component, row * component.across + column);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    bits.flush();
+}
+
+fn encode_block(
+    bits: &mut BitWriter,
+    block: &[i32; 64],
+    first: usize,
+    last: usize,
+    previous: &mut i32,
+    dc: &Codes,
+    ac: &Codes,
+) {
+    if first == 0 {
+        let difference = block[0] - *previous;
+        *previous = block[0];
+        let size = magnitude(difference);
+        bits.code(dc, size);
+        bits.value(difference, size);
+    }
+    if last == 0 {
+        return;
+    }
+
+    let mut run = 0;
+    for &coefficient in &block[first.max(1)..=last] {
+        if coefficient == 0 {
+            run += 1;
+            continue;
+        }
+        // sixteen zeros at a time
+        while run > 15 {
+            bits.code(ac, 0xf0);
+            run -= 16;
+        }
+        let size = magnitude(coefficient);
+        bits.code(ac, (run << 4) | size);
+        bits.value(coefficient, size);
+        run = 0;
+    }
+    // end of block, everything to last is zero
+    if run > 0 {
+        bits.code(ac, 0x00);
+    }
+}
+
+// the number of bits the value needs, what the standard calls its category
+fn magnitude(value: i32) -> u8 {
+    (32 - value.unsigned_abs().leading_zeros()) as u8
+}
+
+struct BitWriter<'a> {
+    out: &'a mut Vec<u8>,
+    accumulator: u32,
+    count: u32,
+}
+
+impl<'a> BitWriter<'a> {
+    fn new(out: &'a mut Vec<u8>) -> Self {
+        Self {
+            out,
+            accumulator: 0,
+            count: 0,
+        }
+    }
+
+    fn write(&mut self, bits: u32, length: u32) {
+        self.accumulator = (self.accumulator << length) | (bits & ((1 << length) - 1));
+        self.count += length;
+        while self.count >= 8 {
+            self.count -= 8;
+            let byte = (self.accumulator >> self.count) as u8;
+            self.out.push(byte);
+            // a 0xff in the data would read as a marker
+            if byte == 0xff {
+                self.out.push(0);
+            }
+        }
+    }
+
+    fn code(&mut self, codes: &Codes, symbol: u8) {
+        let (code, length) = codes[symbol as usize];
+        self.write(code as u32, length as u32);
+    }
+
+    // negative values go in as value - 1 in size bits
+    fn value(&mut self, value: i32, size: u8) {
+        let bits = if value < 0 { value - 1 } else { value };
+        self.write(bits as u32, size as u32);
+    }
+
+    // the last byte is padded out with ones
+    fn flush(&mut self) {
+        if self.count > 0 {
+            let padding = 8 - self.count;
+            self.write((1 << padding) - 1, padding);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // smooth enough to survive quantisation, odd sized so the edges get padded
+    fn gradient(width: u32, height: u32) -> RgbImage {
+        RgbImage::from_fn(width, height, |x, y| {
+            image::Rgb([(x * 4) as u8, (y * 4) as u8, ((x + y) * 2) as u8])
+        })
+    }
+
+    fn round_trip(subsampling: Subsampling, progressive: bool) {
+        let image = gradient(37, 23);
+        let mut jpeg = Vec::new();
+        encode(&mut jpeg, &image, 95, subsampling, progressive).unwrap();
+
+        let decoded = image::load_from_memory_with_format(&jpeg, image::ImageFormat::Jpeg)
+            .unwrap()
+            .to_rgb8();
+        assert_eq!(decoded.dimensions(), image.dimensions());
+        let worst = image
+            .as_raw()
+            .iter()
+            .zip(decoded.as_raw())
+            .map(|(a, b)| a.abs_diff(*b))
+            .max()
+            .unwrap();
+        assert!(
+            worst <= 16,
+            "{subsampling:?} progressive={progressive} is off by {worst}"
+        );
+    }
+
+    #[test]
+    fn baseline_round_trips() {
+        for subsampling in [Subsampling::S444, Subsampling::S422, Subsampling::S420] {
+            round_trip(subsampling, false);
+        }
+    }
+
+    #[test]
+    fn progressive_round_trips() {
+        for subsampling in [Subsampling::S444, Subsampling::S422, Subsampling::S420] {
+            round_trip(subsampling, true);
+        }
+    }
+
+    #[test]
+    fn frames_say_what_they_are() {
+        let image = gradient(16, 16);
+        for (subsampling, progressive, marker, luma_sampling) in [
+            (Subsampling::S444, false, 0xc0, 0x11),
+            (Subsampling::S422, true, 0xc2, 0x21),
+            (Subsampling::S420, false, 0xc0, 0x22),
+        ] {
+            let mut jpeg = Vec::new();
+            encode(&mut jpeg, &image, 90, subsampling, progressive).unwrap();
+            let frame = jpeg
+                .windows(2)
+                .position(|pair| pair[0] == 0xff && (pair[1] == 0xc0 || pair[1] == 0xc2))
+                .unwrap();
+            assert_eq!(jpeg[frame + 1], marker);
+            // marker, length, precision, height, width, count, then Y's id and factors
+            assert_eq!(jpeg[frame + 11], luma_sampling);
+        }
+    }
+
+    #[test]
+    fn parses_subsampling() {
+        assert_eq!("420".parse(), Ok(Subsampling::S420));
+        assert!("411".parse::<Subsampling>().is_err());
+    }
+
+    #[test]
+    fn magnitudes() {
+        assert_eq!(magnitude(0), 0);
+        assert_eq!(magnitude(1), 1);
+        assert_eq!(magnitude(-1), 1);
+        assert_eq!(magnitude(-3), 2);
+        assert_eq!(magnitude(1023), 10);
+    }
+}
@@ -0,0 +1,2727 @@
+use chrono::prelude::*;
+use std::ops::Drop;
+use x11::xlib::*;
+use x11_safe::Event;
+
+mod annotate;
+pub mod args;
+mod backend;
+mod click_through;
+mod clipboard;
+mod config;
+mod countdown;
+mod doctor;
+pub mod error;
+mod events;
+mod hook;
+mod hotkey;
+mod hud;
+mod jpeg;
+mod log;
+mod loupe;
+mod monitor;
+mod output;
+mod palette;
+mod rotation;
+mod scale;
+mod shm;
+mod sidecar;
+mod state;
+mod watch;
+mod wayland;
+mod window;
+mod window_pick;
+mod x11_safe;
+
+use args::Args;
+use error::SleekError;
+use rotation::RotationMode;
+
+const LINE_WIDTH: i32 = 5;
+const MINIMAL_LINE_WIDTH: i32 = 1;
+const MAX_LINE_WIDTH: i32 = 50;
+const REFRESH_RATE: u32 = 60;
+// outside this --refresh-rate is either a slideshow or just burns CPU
+const REFRESH_RATES: std::ops::RangeInclusive<u32> = 10..=240;
+// the DPI the default line width and loupe zoom were picked at, denser screens
+// scale them up
+const REFERENCE_DPI: f64 = 96.0;
+// how long to give the screen to repaint once the overlay is out of the way
+const SETTLE_TIME: std::time::Duration = std::time::Duration::from_millis(50);
+const DEFAULT_LINE_COLOUR: RGB = RGB::new(128, 0, 128);
+const OVERLAY_RADIUS: i32 = 12;
+
+// a window manager that bound sleek to a key often still holds the grab for a moment
+const GRAB_ATTEMPTS: u32 = 20;
+const GRAB_RETRY_DELAY: std::time::Duration = std::time::Duration::from_millis(50);
+
+const POINTER_MASK: i64 = ButtonPressMask | ButtonReleaseMask | PointerMotionMask;
+
+const XNONE: u64 = 0;
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Copy, Clone, Debug)]
+pub struct RGB {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl RGB {
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+}
+
+impl std::fmt::Display for RGB {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
+
+// RRGGBB with or without a leading #
+impl std::str::FromStr for RGB {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.strip_prefix('#').unwrap_or(s);
+        if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("'{s}' is not a RRGGBB hex colour"));
+        }
+
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap();
+        Ok(Self::new(channel(0), channel(2), channel(4)))
+    }
+}
+
+enum SelectionState {
+    NotCreated,
+    Selecting,
+    Selected,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Point {
+    pub fn new(x: i32, y: i32) -> Self {
+        Self { x, y }
+    }
+    pub fn min(&self, other: &Point) -> Self {
+        Self {
+            x: self.x.min(other.x),
+            y: self.y.min(other.y),
+        }
+    }
+    pub fn max(&self, other: &Point) -> Self {
+        Self {
+            x: self.x.max(other.x),
+            y: self.y.max(other.y),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Rect {
+    pub fn new(x: i32, y: i32, width: i32, height: i32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn corners(&self) -> (Point, Point) {
+        (
+            Point::new(self.x, self.y),
+            Point::new(self.x + self.width, self.y + self.height),
+        )
+    }
+
+    // the overlap of both, None if they don't touch
+    pub fn intersect(&self, other: &Rect) -> Option<Rect> {
+        let (min, max) = self.corners();
+        let (other_min, other_max) = other.corners();
+        let min = min.max(&other_min);
+        let max = max.min(&other_max);
+        (min.x < max.x && min.y < max.y)
+            .then(|| Rect::new(min.x, min.y, max.x - min.x, max.y - min.y))
+    }
+
+    // what's left of self with other cut out, as up to four rects
+    pub fn subtract(&self, other: &Rect) -> Vec<Rect> {
+        let (min, max) = self.corners();
+        let (other_min, other_max) = other.corners();
+        let inner_min = min.max(&other_min);
+        let inner_max = max.min(&other_max);
+        if inner_min.x >= inner_max.x || inner_min.y >= inner_max.y {
+            return vec![*self];
+        }
+
+        [
+            Rect::new(min.x, min.y, self.width, inner_min.y - min.y),
+            Rect::new(min.x, inner_max.y, self.width, max.y - inner_max.y),
+            Rect::new(
+                min.x,
+                inner_min.y,
+                inner_min.x - min.x,
+                inner_max.y - inner_min.y,
+            ),
+            Rect::new(
+                inner_max.x,
+                inner_min.y,
+                max.x - inner_max.x,
+                inner_max.y - inner_min.y,
+            ),
+        ]
+        .into_iter()
+        .filter(|rect| rect.width > 0 && rect.height > 0)
+        .collect()
+    }
+}
+
+impl std::str::FromStr for Rect {
+    type Err = String;
+
+    // x,y,w,h, slurp's "x,y wxh" or X's WxH+X+Y
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some((size, position)) = s.trim().split_once('+') {
+            let (width, height) = size.split_once('x').ok_or("expected WxH+X+Y")?;
+            let (x, y) = position.split_once('+').ok_or("expected WxH+X+Y")?;
+            return format!("{x},{y},{width},{height}").parse();
+        }
+
+        let s = match s
+            .trim()
+            .split_once(' ')
+            .filter(|(_, size)| size.contains('x'))
+        {
+            Some((position, size)) => format!("{position},{}", size.replace('x', ",")),
+            None => s.to_owned(),
+        };
+
+        let values = s
+            .split(',')
+            .map(|v| v.trim().parse::<i32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+
+        match values[..] {
+            [x, y, width, height] if width > 0 && height > 0 => Ok(Self::new(x, y, width, height)),
+            [_, _, _, _] => Err("width and height must be positive".to_owned()),
+            _ => Err("expected x,y,w,h or \"x,y wxh\"".to_owned()),
+        }
+    }
+}
+
+// width:height reduced to lowest terms, so any selection locked to it is a
+// whole multiple of both
+#[derive(Copy, Clone, Debug)]
+pub struct Ratio {
+    width: i32,
+    height: i32,
+}
+
+impl std::str::FromStr for Ratio {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (width, height) = s.split_once(':').ok_or("expected W:H")?;
+        let width: i32 = width.trim().parse().map_err(|e| format!("{e}"))?;
+        let height: i32 = height.trim().parse().map_err(|e| format!("{e}"))?;
+        if width <= 0 || height <= 0 {
+            return Err("both sides must be positive".to_owned());
+        }
+
+        let gcd = gcd(width, height);
+        Ok(Self {
+            width: width / gcd,
+            height: height / gcd,
+        })
+    }
+}
+
+// what the selection cuts out of its bounding box
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Shape {
+    Rectangle,
+    Ellipse,
+}
+
+impl std::str::FromStr for Shape {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rectangle" => Ok(Self::Rectangle),
+            "ellipse" => Ok(Self::Ellipse),
+            _ => Err("expected 'rectangle' or 'ellipse'".to_owned()),
+        }
+    }
+}
+
+fn gcd(a: i32, b: i32) -> i32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+pub struct ScreenData {
+    width: i32,
+    height: i32,
+    rmask: u32,
+    gmask: u32,
+    bmask: u32,
+    // whatever bits of the depth the colour doesn't use, zero below 32 bits
+    amask: u32,
+}
+
+impl ScreenData {
+    pub fn new(width: i32, height: i32, depth: u32, rmask: u32, gmask: u32, bmask: u32) -> Self {
+        let depth_mask = if depth >= 32 {
+            u32::MAX
+        } else {
+            (1 << depth) - 1
+        };
+        Self {
+            width,
+            height,
+            rmask,
+            gmask,
+            bmask,
+            amask: depth_mask & !(rmask | gmask | bmask),
+        }
+    }
+
+    pub fn has_alpha(&self) -> bool {
+        self.amask != 0
+    }
+
+    // ARGB visuals hold premultiplied colour, PNG wants it straight. opaque
+    // when there's no alpha channel
+    pub fn decode_rgba(&self, pixel: u32) -> [u8; 4] {
+        let colour = self.decode(pixel);
+        if !self.has_alpha() {
+            return [colour.r, colour.g, colour.b, 255];
+        }
+
+        let alpha = unpack(pixel, self.amask);
+        let unpremultiply = |c: u8| match alpha {
+            0 => 0,
+            a => (c as u32 * 255 / a as u32).min(255) as u8,
+        };
+        [
+            unpremultiply(colour.r),
+            unpremultiply(colour.g),
+            unpremultiply(colour.b),
+            alpha,
+        ]
+    }
+
+    // the other way round, for foreground colours. channels are stretched to
+    // however many bits their mask has, and alpha is opaque
+    pub fn encode(&self, colour: RGB) -> u64 {
+        let channel = |value: u8, mask: u32| {
+            let shift = mask.trailing_zeros() % 32;
+            let max = (mask >> shift) as u64;
+            (value as u64 * max / 255) << shift
+        };
+        channel(colour.r, self.rmask)
+            | channel(colour.g, self.gmask)
+            | channel(colour.b, self.bmask)
+            | self.amask as u64
+    }
+
+    pub fn decode(&self, pixel: u32) -> RGB {
+        RGB::new(
+            unpack(pixel, self.rmask),
+            unpack(pixel, self.gmask),
+            unpack(pixel, self.bmask),
+        )
+    }
+}
+
+// one channel scaled from however many bits its mask has to 0-255, so 565
+// and 10 bit visuals come out at full range like 888 does
+fn unpack(pixel: u32, mask: u32) -> u8 {
+    let shift = mask.trailing_zeros() % 32;
+    let max = (mask >> shift) as u64;
+    if max == 0 {
+        return 0;
+    }
+    let value = ((pixel & mask) >> shift) as u64;
+    ((value * 255 + max / 2) / max) as u8
+}
+
+pub struct RenderWindow {
+    display: x11_safe::Display,
+    root: u64,
+    window: u64,
+    pixmap: u64,
+    dimmed: Option<u64>,
+    // every frame is put together here and copied to the window in one go,
+    // the window itself is never drawn on so it never shows half a frame
+    buffer: u64,
+    graphics_context: x11_safe::Gc,
+    font: Option<x11_safe::Font>,
+    screen_data: ScreenData,
+    // where the overlay sits on the root, non zero with --monitor
+    origin: Point,
+    // taken before the overlay is mapped, after that the pointer shows sleek's own cursor
+    cursor: Option<x11_safe::CursorImage>,
+    // --no-freeze, the window is shaped down to what's drawn on it so the
+    // screen underneath stays live
+    live: bool,
+    // display DPI over REFERENCE_DPI, never below 1. 1 with --no-scale
+    scale: f64,
+    // last place the pointer was seen over the overlay
+    pointer: Point,
+    // Shift dragged areas inside the selection that get blurred on save, in
+    // overlay coordinates
+    redactions: Vec<Rect>,
+    // top level windows on the root, only collected when something needs them
+    windows: Vec<Rect>,
+    // everything painted over the background since the buffer was last cleared
+    drawn: Vec<Rect>,
+    // parts of the buffer the window hasn't caught up with yet
+    damaged: Vec<Rect>,
+    line_colour: RGB,
+    pointer_grabbed: bool,
+    keyboard_grabbed: bool,
+    // last so it's closed after everything above has been freed on it
+    _connection: x11_safe::Connection,
+}
+
+impl RenderWindow {
+    // takes ownership of the raw handles and frees them on drop, so only
+    // init_x gets to build one
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        connection: x11_safe::Connection,
+        root: u64,
+        window: u64,
+        pixmap: u64,
+        dimmed: Option<u64>,
+        buffer: u64,
+        graphics_context: x11_safe::Gc,
+        font: Option<x11_safe::Font>,
+        screen_data: ScreenData,
+        origin: Point,
+        line_colour: RGB,
+    ) -> Self {
+        Self {
+            display: connection.display(),
+            root,
+            window,
+            pixmap,
+            dimmed,
+            buffer,
+            graphics_context,
+            font,
+            screen_data,
+            origin,
+            cursor: None,
+            live: false,
+            scale: 1.0,
+            pointer: Point::new(0, 0),
+            redactions: Vec::new(),
+            windows: Vec::new(),
+            drawn: Vec::new(),
+            damaged: Vec::new(),
+            line_colour,
+            pointer_grabbed: false,
+            keyboard_grabbed: false,
+            _connection: connection,
+        }
+    }
+
+    // a size picked at REFERENCE_DPI, in pixels on this display
+    pub fn scaled(&self, size: i32) -> i32 {
+        (size as f64 * self.scale).round() as i32
+    }
+
+    // the grabs are owned here so that Drop releases them no matter how we exit,
+    // a leaked grab leaves the whole desktop unusable
+    pub fn grab_input(&mut self) {
+        let (display, root, window) = (self.display, self.root, self.window);
+
+        // without the grabs the overlay still works, it just might miss events.
+        // confined to the root so the pointer can't wander onto another screen
+        self.pointer_grabbed =
+            grab_with_retry(|| x11_safe::grab_pointer(display, window, root, POINTER_MASK));
+        if !self.pointer_grabbed {
+            log::warn("could not grab the pointer, continuing without it");
+        }
+
+        self.keyboard_grabbed = grab_with_retry(|| x11_safe::grab_keyboard(display, window));
+        if !self.keyboard_grabbed {
+            log::warn("could not grab the keyboard, continuing without it");
+        }
+    }
+
+    // what the window shows with nothing drawn over it
+    fn background(&self) -> u64 {
+        self.dimmed.unwrap_or(self.pixmap)
+    }
+
+    // rect on screen, None when none of it is
+    fn on_screen(&self, rect: Rect) -> Option<Rect> {
+        rect.intersect(&Rect::new(
+            0,
+            0,
+            self.screen_data.width,
+            self.screen_data.height,
+        ))
+    }
+
+    fn copy(&self, source: u64, destination: u64, rect: Rect) {
+        if let Some(rect) = self.on_screen(rect) {
+            x11_safe::copy_area(
+                self.display,
+                source,
+                destination,
+                self.graphics_context,
+                rect.x,
+                rect.y,
+                rect.width as u32,
+                rect.height as u32,
+            );
+        }
+    }
+
+    // puts the background back under rect in the buffer
+    pub fn restore(&mut self, rect: Rect) {
+        self.copy(self.background(), self.buffer, rect);
+        self.damaged.push(rect);
+    }
+
+    // source's pixels under rect go into the buffer as part of this frame
+    pub fn copy_to_buffer(&mut self, source: u64, rect: Rect) {
+        self.copy(source, self.buffer, rect);
+        self.mark_drawn(rect);
+    }
+
+    // called for everything drawn into the buffer so the next frame can undo it
+    pub fn mark_drawn(&mut self, rect: Rect) {
+        self.drawn.push(rect);
+        self.damaged.push(rect);
+    }
+
+    // copies whatever changed in the buffer to the window
+    pub fn present(&mut self) {
+        for rect in std::mem::take(&mut self.damaged) {
+            self.copy(self.buffer, self.window, rect);
+        }
+        if self.live {
+            let visible: Vec<_> = self
+                .drawn
+                .iter()
+                .filter_map(|rect| self.on_screen(*rect))
+                .map(|rect| (rect.x, rect.y, rect.width as u32, rect.height as u32))
+                .collect();
+            x11_safe::set_visible_rects(self.display, self.window, &visible);
+        }
+    }
+
+    // takes a fresh copy of the screen under the overlay as the background
+    pub fn snapshot(&mut self) -> Result<(), x11_safe::Error> {
+        let mut image = x11_safe::get_image(
+            self.display,
+            self.root,
+            self.origin.x,
+            self.origin.y,
+            self.screen_data.width as u32,
+            self.screen_data.height as u32,
+        )?;
+        load_background(self, &mut image);
+        Ok(())
+    }
+
+    // --continuous, the overlay steps aside long enough to snapshot the screen
+    // as it is now and comes back ready for the next selection
+    pub fn refreeze(&mut self) -> Result<(), x11_safe::Error> {
+        self.release_input();
+        if !self.live {
+            x11_safe::unmap_window(self.display, self.window);
+        }
+        x11_safe::sync(self.display);
+        std::thread::sleep(SETTLE_TIME);
+
+        self.snapshot()?;
+        self.redactions.clear();
+
+        if !self.live {
+            x11_safe::map_raised(self.display, self.window);
+            x11_safe::sync(self.display);
+        }
+        x11_safe::set_input_focus(self.display, self.window);
+        self.grab_input();
+        self.repaint();
+        Ok(())
+    }
+
+    // takes everything off the buffer without showing it yet
+    pub fn reset(&mut self) {
+        for rect in std::mem::take(&mut self.drawn) {
+            self.restore(rect);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.reset();
+        self.present();
+    }
+
+    // the whole window, for when the background itself has changed
+    pub fn repaint(&mut self) {
+        self.drawn.clear();
+        self.damaged = vec![Rect::new(
+            0,
+            0,
+            self.screen_data.width,
+            self.screen_data.height,
+        )];
+        self.present();
+    }
+
+    pub fn release_input(&mut self) {
+        if self.pointer_grabbed {
+            x11_safe::ungrab_pointer(self.display);
+            self.pointer_grabbed = false;
+        }
+        if self.keyboard_grabbed {
+            x11_safe::ungrab_keyboard(self.display);
+            self.keyboard_grabbed = false;
+        }
+        x11_safe::sync(self.display);
+    }
+}
+
+impl Drop for RenderWindow {
+    fn drop(&mut self) {
+        self.release_input();
+
+        drop(self.font.take());
+        x11_safe::free_gc(self.display, self.graphics_context);
+        x11_safe::free_pixmap(self.display, self.pixmap);
+        x11_safe::free_pixmap(self.display, self.buffer);
+        if let Some(dimmed) = self.dimmed {
+            x11_safe::free_pixmap(self.display, dimmed);
+        }
+        x11_safe::destroy_window(self.display, self.window);
+    }
+}
+
+// the stroke is centred on the selection's edges, so roughly half of it lies
+// inside the region. it never ends up in a capture since the window is cleared
+// before grabbing, and --selection-outline-only redraws the same geometry.
+// only the defaults follow the DPI, an explicit --line-width is in pixels
+fn line_width(args: &Args, scale: f64) -> i32 {
+    let scaled = |width: i32| ((width as f64 * scale).round() as i32).clamp(1, MAX_LINE_WIDTH);
+    match args.line_width {
+        Some(width) => width,
+        None if args.minimal => scaled(MINIMAL_LINE_WIDTH),
+        None => scaled(LINE_WIDTH),
+    }
+}
+
+// XWayland only sees its own clients, under most compositors the root comes
+// back black or with just the X windows on it and sleek looks broken
+fn warn_about_wayland(args: &Args) {
+    if args.force || !wayland::session() || native_wayland(args) {
+        return;
+    }
+    log::warn(
+        "WAYLAND_DISPLAY is set, sleek captures through XWayland which usually \
+         can't see native Wayland windows, so the capture may be black or \
+         incomplete. --geometry and --fullscreen capture natively on wlroots \
+         compositors, --force hides this warning",
+    );
+}
+
+// --geometry and --fullscreen don't need the overlay, so under Wayland they can
+// ask the compositor directly instead of going through XWayland
+pub(crate) fn native_wayland(args: &Args) -> bool {
+    wayland::session()
+        && !args.force
+        && (args.geometry.is_some() || args.fullscreen)
+        && args.relative_to_active.is_none()
+        && !args.active
+        && args.window_id.is_none()
+        && args.wait_for_pid.is_none()
+}
+
+// the overlay's pixmaps are filled straight from the root, so only visuals at
+// the root's depth will do. the default one first since it needs no colormap
+// of its own, then TrueColor over DirectColor. some VNC and nested servers
+// offer nothing but DirectColor
+pub(crate) fn pick_visual(
+    display: x11_safe::Display,
+    screen_number: i32,
+    depth: u32,
+) -> Option<XVisualInfo> {
+    let default = x11_safe::default_visual_id(display, screen_number);
+    x11_safe::visuals(display, screen_number)
+        .into_iter()
+        .filter(|visual| visual.depth as u32 == depth)
+        .filter(|visual| visual.class == TrueColor || visual.class == DirectColor)
+        .min_by_key(|visual| (visual.visualid != default, visual.class != TrueColor))
+}
+
+// a DirectColor colormap is loaded with a straight ramp so every pixel means
+// what ScreenData takes it to mean. it goes away with the connection
+fn visual_colormap(display: x11_safe::Display, root: u64, visual: &XVisualInfo) -> u64 {
+    if visual.class != DirectColor {
+        return x11_safe::create_colormap(display, root, visual.visual, AllocNone);
+    }
+
+    let colormap = x11_safe::create_colormap(display, root, visual.visual, AllocAll);
+    let last = visual.colormap_size.max(2) as u64 - 1;
+    // entry i sits at the same fraction of every channel's range
+    let field = |mask: u64, i: u64| {
+        let max = mask >> mask.trailing_zeros();
+        (i * max / last) << mask.trailing_zeros()
+    };
+    let mut ramp: Vec<XColor> = (0..=last)
+        .map(|i| {
+            let level = (i * u16::MAX as u64 / last) as u16;
+            XColor {
+                pixel: field(visual.red_mask, i)
+                    | field(visual.green_mask, i)
+                    | field(visual.blue_mask, i),
+                red: level,
+                green: level,
+                blue: level,
+                flags: DoRed | DoGreen | DoBlue,
+                pad: 0,
+            }
+        })
+        .collect();
+    x11_safe::store_colors(display, colormap, &mut ramp);
+    colormap
+}
+
+// happens before anything is grabbed so menus and tooltips opened in the
+// meantime end up in the background snapshot
+fn print_countdown(seconds: u64) {
+    for remaining in (1..=seconds).rev() {
+        eprintln!("capturing in {remaining}...");
+        std::thread::sleep(std::time::Duration::from_secs(1));
+    }
+}
+
+pub fn init_x(args: &Args) -> Result<RenderWindow, SleekError> {
+    let connection = x11_safe::open_display().map_err(|_| SleekError::DisplayOpenFailed)?;
+    let display = connection.display();
+
+    let screen_number = match args.screen_number {
+        // XRootWindow indexes the screen array without checking
+        Some(n) if (0..x11_safe::screen_count(display)).contains(&n) => n,
+        Some(n) => return Err(SleekError::NoScreen(n)),
+        None => x11_safe::default_screen(display),
+    };
+    log::info(&format!("opened the display, using screen {screen_number}"));
+
+    let mut window_attributes = XSetWindowAttributes {
+        background_pixmap: XNONE,
+        background_pixel: 0,
+        border_pixmap: CopyFromParent as u64,
+        border_pixel: 0,
+        bit_gravity: ForgetGravity,
+        win_gravity: NorthWestGravity,
+        backing_store: NotUseful,
+        backing_planes: u64::MAX,
+        backing_pixel: 0,
+        save_under: 0,
+        event_mask: 0,
+        do_not_propagate_mask: 0,
+        override_redirect: 1,
+        colormap: CopyFromParent as u64,
+        cursor: XNONE,
+    };
+
+    let geometry = x11_safe::get_geometry(display, x11_safe::root_window(display, screen_number))?;
+    let root = geometry.root;
+    let (mut x, mut y) = (geometry.x, geometry.y);
+    let (mut width, mut height, depth) = (geometry.width, geometry.height, geometry.depth);
+
+    // the overlay only covers the chosen CRTC, everything in the event loop is
+    // then relative to that monitor's corner
+    let monitor = match args.monitor {
+        Some(n) => Some(
+            *monitor::monitors(display, root)
+                .get(n)
+                .ok_or(SleekError::NoMonitor(n))?,
+        ),
+        // without RandR there's nothing to pick from and the whole root is used
+        None if args.current_monitor => x11_safe::query_pointer(display, root)
+            .and_then(|(x, y)| monitor::monitor_at(display, root, Point::new(x, y))),
+        None => None,
+    };
+    if let Some(monitor) = monitor {
+        log::info(&format!(
+            "confined to the monitor at {}x{}+{}+{}",
+            monitor.width, monitor.height, monitor.x, monitor.y
+        ));
+        (x, y) = (monitor.x, monitor.y);
+        (width, height) = (monitor.width as u32, monitor.height as u32);
+    }
+
+    let scale = match x11_safe::dpi(display, screen_number) {
+        Some(dpi) if args.dpi_scale => (dpi / REFERENCE_DPI).max(1.0),
+        _ => 1.0,
+    };
+
+    let visual_info =
+        pick_visual(display, screen_number, depth).ok_or(SleekError::NoUsableVisual)?;
+
+    let screen_data = ScreenData::new(
+        width as i32,
+        height as i32,
+        depth,
+        visual_info.red_mask as u32,
+        visual_info.green_mask as u32,
+        visual_info.blue_mask as u32,
+    );
+    // the first thing to check when the colours come out wrong
+    log::info(&format!(
+        "visual 0x{:x}: depth {depth}, red 0x{:08x} green 0x{:08x} blue 0x{:08x}",
+        visual_info.visualid, visual_info.red_mask, visual_info.green_mask, visual_info.blue_mask
+    ));
+
+    // only the default visual can borrow the root's colormap
+    let mut window_mask = CWOverrideRedirect;
+    if visual_info.visualid != x11_safe::default_visual_id(display, screen_number) {
+        window_attributes.colormap = visual_colormap(display, root, &visual_info);
+        window_mask |= CWColormap | CWBorderPixel;
+    }
+
+    let window = x11_safe::create_window(
+        display,
+        root,
+        x,
+        y,
+        width,
+        height,
+        geometry.border_width,
+        depth as i32,
+        visual_info.visual,
+        window_mask,
+        &mut window_attributes,
+    )?;
+
+    let pixmap = x11_safe::create_pixmap(display, window, width, height, depth)?;
+
+    x11_safe::set_standard_properties(display, window, "sleek", "icon");
+
+    x11_safe::select_input(display, window, POINTER_MASK | KeyPressMask);
+
+    let mut gc_values = XGCValues {
+        function: GXcopy,
+        plane_mask: u64::MAX,
+        foreground: screen_data.encode(args.line_colour),
+        background: 0,
+        line_width: line_width(args, scale),
+        line_style: LineSolid,
+        cap_style: CapButt,
+        join_style: JoinMiter,
+        fill_style: FillSolid,
+        fill_rule: EvenOddRule,
+        arc_mode: ArcPieSlice,
+        tile: 0,
+        stipple: u64::MAX,
+        ts_x_origin: 0,
+        ts_y_origin: 0,
+        font: 0,
+        subwindow_mode: ClipByChildren,
+        graphics_exposures: True,
+        clip_x_origin: 0,
+        clip_y_origin: 0,
+        clip_mask: XNONE,
+        dash_offset: 0,
+        dashes: 4,
+    };
+
+    let graphics_context = x11_safe::create_gc(
+        display,
+        root,
+        (GCLineWidth | GCForeground) as u64,
+        &mut gc_values,
+    )?;
+
+    // text is optional, without a font the hud is simply not drawn
+    let font = x11_safe::Font::load(display, "fixed").ok();
+    if let Some(font) = &font {
+        font.set_on(display, graphics_context);
+    }
+
+    // the colour pickers want to see true colours everywhere, not just inside
+    // a selection. a live overlay has nothing to dim
+    let dimmed = if !args.minimal && !args.palette_pick && !args.pick && args.freeze {
+        Some(x11_safe::create_pixmap(
+            display, window, width, height, depth,
+        )?)
+    } else {
+        None
+    };
+    let buffer = x11_safe::create_pixmap(display, window, width, height, depth)?;
+
+    let mut render_window = RenderWindow::new(
+        connection,
+        root,
+        window,
+        pixmap,
+        dimmed,
+        buffer,
+        graphics_context,
+        font,
+        screen_data,
+        Point::new(x, y),
+        args.line_colour,
+    );
+    render_window.scale = scale;
+
+    if args.window_pick || args.snap {
+        render_window.windows = window::top_level_rects(display, root);
+    }
+
+    if args.cursor {
+        match x11_safe::cursor_image(display) {
+            Ok(cursor) => render_window.cursor = Some(cursor),
+            Err(e) => log::warn(&format!("{e}, capturing without the cursor")),
+        }
+    }
+
+    let mut image = x11_safe::get_image(display, root, x, y, width, height)?;
+    load_background(&render_window, &mut image);
+    // the pixels live on in the pixmaps, the client side copy isn't needed any more
+    drop(image);
+
+    // the grab uses the window's cursor, so this holds for the whole selection
+    x11_safe::define_crosshair_cursor(display, window);
+
+    // nothing is drawn yet, so none of the window is shown
+    if !args.freeze {
+        render_window.live = true;
+        x11_safe::set_visible_rects(display, window, &[]);
+    }
+
+    // fully transparent on the 32 bit visual, so the desktop shows through
+    // when a compositor is running
+    if args.click_through.is_some() {
+        x11_safe::set_window_background(display, window, 0);
+        x11_safe::set_click_through(display, window, true);
+    }
+
+    x11_safe::map_raised(display, window);
+
+    // input is only taken once click through mode is left
+    if args.click_through.is_none() {
+        x11_safe::set_input_focus(display, window);
+        render_window.grab_input();
+    }
+    Ok(render_window)
+}
+
+// the bright copy is what gets captured, the dimmed one (if any) is what the
+// window shows outside of the selection
+fn load_background(render_window: &RenderWindow, image: &mut x11_safe::Image) {
+    let (display, gc) = (render_window.display, render_window.graphics_context);
+    let (width, height) = (image.width() as u32, image.height() as u32);
+
+    x11_safe::put_image(
+        display,
+        render_window.pixmap,
+        gc,
+        image,
+        0,
+        0,
+        0,
+        0,
+        width,
+        height,
+    );
+
+    let Some(dimmed) = render_window.dimmed else {
+        x11_safe::set_window_background_pixmap(display, render_window.window, render_window.pixmap);
+        fill_buffer(render_window, width, height);
+        return;
+    };
+
+    let screen_data = &render_window.screen_data;
+    let colour_masks = [screen_data.rmask, screen_data.gmask, screen_data.bmask];
+    let alpha_mask = !(screen_data.rmask | screen_data.gmask | screen_data.bmask);
+    // halve every channel in place, the bit shifted out of one channel
+    // into the next is masked away again
+    image.map_pixels(|pixel| {
+        colour_masks.iter().fold(pixel & alpha_mask, |dim, mask| {
+            dim | ((pixel & mask) >> 1) & mask
+        })
+    });
+
+    x11_safe::put_image(display, dimmed, gc, image, 0, 0, 0, 0, width, height);
+    x11_safe::set_window_background_pixmap(display, render_window.window, dimmed);
+    fill_buffer(render_window, width, height);
+}
+
+// the buffer starts out as a plain copy of the background
+fn fill_buffer(render_window: &RenderWindow, width: u32, height: u32) {
+    x11_safe::copy_area(
+        render_window.display,
+        render_window.background(),
+        render_window.buffer,
+        render_window.graphics_context,
+        0,
+        0,
+        width,
+        height,
+    );
+}
+
+fn grab_with_retry(grab: impl Fn() -> Result<(), x11_safe::Error>) -> bool {
+    for attempt in 1..=GRAB_ATTEMPTS {
+        match grab() {
+            Ok(()) => return true,
+            Err(e) => {
+                log::debug(&format!("{e} (attempt {attempt}/{GRAB_ATTEMPTS})"));
+                std::thread::sleep(GRAB_RETRY_DELAY);
+            }
+        }
+    }
+    false
+}
+
+// X screens (:0.0, :0.1, ...) are separate roots with their own visuals, unlike
+// monitors which are just areas of a single root
+fn list_screens() -> Result<(), SleekError> {
+    let connection = x11_safe::open_display().map_err(|_| SleekError::DisplayOpenFailed)?;
+    let display = connection.display();
+
+    let default_screen = x11_safe::default_screen(display);
+    for n in 0..x11_safe::screen_count(display) {
+        let (width, height) = x11_safe::display_size(display, n);
+        println!(
+            "{n}: {width}x{height} depth {}{}",
+            x11_safe::default_depth(display, n),
+            if n == default_screen {
+                " (default)"
+            } else {
+                ""
+            }
+        );
+    }
+
+    Ok(())
+}
+
+// everything the sleek binary does, main is just this
+pub fn run() {
+    let args = Args::parse();
+    log::set_level(args.verbosity);
+
+    if args.list_screens {
+        if let Err(e) = list_screens() {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if args.doctor {
+        if let Err(e) = doctor::report() {
+            eprintln!("--doctor: {e}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    warn_about_wayland(&args);
+
+    if let Some(keysym) = &args.wait_for_key {
+        let timeout = args.timeout.map(std::time::Duration::from_secs);
+        if let Err(e) = hotkey::wait_for_key(keysym, timeout) {
+            eprintln!("--wait-for-key: {e}");
+            std::process::exit(1);
+        }
+    }
+
+    if let Some(seconds) = args.delay {
+        match countdown::run(&args, seconds) {
+            Ok(true) => {}
+            // Escape during the countdown quits like it does on the overlay
+            Ok(false) => return,
+            Err(e) => {
+                log::warn(&format!("{e}, counting down here instead"));
+                print_countdown(seconds);
+            }
+        }
+    }
+
+    let region = args.relative_to_active.map(|offset| {
+        window::relative_to_active(offset).unwrap_or_else(|e| {
+            eprintln!("--relative-to-active: {e}");
+            std::process::exit(1);
+        })
+    });
+
+    let active = args.active.then(|| {
+        window::active_window(args.client_only).unwrap_or_else(|e| {
+            eprintln!("--active: {e}");
+            std::process::exit(1);
+        })
+    });
+    let fullscreen = args.fullscreen || matches!(active, Some(None));
+    if matches!(active, Some(None)) {
+        log::warn("no active window, capturing the whole screen");
+    }
+    let region = region.or(active.flatten().map(|rect| rect.corners()));
+
+    let window_id = args.window_id.or_else(|| {
+        args.wait_for_pid.map(|pid| {
+            let timeout = args.timeout.map(std::time::Duration::from_secs);
+            window::wait_for_pid(pid, timeout).unwrap_or_else(|e| {
+                eprintln!("--wait-for-pid: {e}");
+                std::process::exit(1);
+            })
+        })
+    });
+
+    if let Some(window) = window_id {
+        let capture = window::capture_window(window, args.client_only).unwrap_or_else(|e| {
+            eprintln!("--window-id: {e}");
+            std::process::exit(1);
+        });
+        if let Err(e) =
+            output_path(&args).and_then(|filepath| write_window_capture(&args, &filepath, capture))
+        {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+        copy_saved_path(&args);
+        return;
+    }
+
+    // a known region doesn't need the overlay at all. read once, "-" can only
+    // be read from stdin the one time
+    let rect = args.geometry.as_deref().map(|geometry| {
+        read_geometry(geometry).unwrap_or_else(|e| {
+            eprintln!("--geometry: {e}");
+            std::process::exit(1);
+        })
+    });
+    let mut backends = backend::select(&args).into_iter().peekable();
+    while let Some(backend) = backends.next() {
+        match backend.capture(rect, args.cursor) {
+            Ok(capture) => {
+                if let Err(e) = output_path(&args)
+                    .and_then(|filepath| write_window_capture(&args, &filepath, capture))
+                {
+                    eprintln!("{e}");
+                    std::process::exit(1);
+                }
+                copy_saved_path(&args);
+                return;
+            }
+            Err(e) if backends.peek().is_none() && rect.is_some() => {
+                eprintln!("--geometry: {e}");
+                std::process::exit(1);
+            }
+            // GNOME and KDE don't have wlr-screencopy, their XWayland may still work
+            Err(e) => log::warn(&format!("{e}, capturing through XWayland instead")),
+        }
+    }
+
+    let initial = args.initial_geometry.as_deref().map(|geometry| {
+        read_geometry(geometry).unwrap_or_else(|e| {
+            eprintln!("--initial-geometry: {e}");
+            std::process::exit(1);
+        })
+    });
+
+    let mut render_window = init_x(&args).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(1);
+    });
+
+    let initial = match initial {
+        Some(rect) => onto_overlay(&render_window, rect, "--initial-geometry"),
+        None if args.last => last_selection(&render_window),
+        None => None,
+    };
+
+    if let Some(keysym) = &args.click_through {
+        if let Err(e) = click_through::wait_to_engage(&mut render_window, keysym) {
+            eprintln!("--click-through: {e}");
+            drop(render_window);
+            std::process::exit(1);
+        }
+    }
+
+    let result = if let Some((point_one, point_two)) = region {
+        // the active window's rect is in root coordinates
+        let origin = render_window.origin;
+        let point_one = Point::new(point_one.x - origin.x, point_one.y - origin.y);
+        let point_two = Point::new(point_two.x - origin.x, point_two.y - origin.y);
+        save_selection(&mut render_window, &args, point_one, point_two)
+    } else if fullscreen {
+        save_fullscreen(&mut render_window, &args)
+    } else if args.window_pick {
+        window_pick::pick_window(&mut render_window, &args)
+    } else if args.pick {
+        palette::pick_colour(&mut render_window, &args)
+    } else if args.palette_pick {
+        palette::pick_palette(&mut render_window, &args)
+    } else {
+        handle_events(&mut render_window, &args, initial)
+    };
+
+    // the window has to be dropped before exiting so the grabs are released
+    if let Err(e) = result {
+        drop(render_window);
+        eprintln!("{e}");
+        std::process::exit(1);
+    }
+    drop(render_window);
+    copy_saved_path(&args);
+}
+
+// --copy-path waits until everything is written and the overlay is gone, like
+// --clipboard it then keeps running until something else is copied
+fn copy_saved_path(args: &Args) {
+    if !args.copy_path {
+        return;
+    }
+    // nothing was saved to a file, or --clipboard has served it already
+    let Some(path) = take_last_saved() else {
+        return;
+    };
+    let text = path.to_string_lossy();
+    let contents = clipboard::Contents {
+        text: Some(&text),
+        ..Default::default()
+    };
+    if let Err(e) = clipboard::serve_detached(&contents) {
+        eprintln!("--copy-path: {e}");
+    }
+}
+
+// grabs rect (in root coordinates) straight off the screen, without the overlay
+pub fn capture_region(rect: Rect) -> Result<image::RgbImage, SleekError> {
+    window::capture_rect(rect)
+        .map(|capture| capture.image.into_rgb8())
+        .map_err(SleekError::Capture)
+}
+
+// runs the overlay until the user picks a region, returned in root
+// coordinates. None when they gave up with ESC
+pub fn select_region(args: &Args) -> Result<Option<Rect>, SleekError> {
+    let mut render_window = init_x(args)?;
+
+    let mut events = events::Stream::new(args.emit_events, render_window.origin, frame_time(args));
+    let (min, max) = match select(&mut render_window, args, None, &mut events) {
+        None => return Ok(None),
+        Some(Selection::Fullscreen) => (
+            Point::new(0, 0),
+            Point::new(
+                render_window.screen_data.width,
+                render_window.screen_data.height,
+            ),
+        ),
+        Some(Selection::Region(point_one, point_two)) => {
+            (point_one.min(&point_two), point_one.max(&point_two))
+        }
+    };
+
+    events.commit(rect_between(min, max));
+
+    let origin = render_window.origin;
+    Ok(Some(Rect::new(
+        min.x + origin.x,
+        min.y + origin.y,
+        max.x - min.x,
+        max.y - min.y,
+    )))
+}
+
+// --last moved onto the overlay and cut down to what's on screen now, the
+// monitors may have changed since it was saved
+fn last_selection(render_window: &RenderWindow) -> Option<Rect> {
+    let rect = match state::load_last() {
+        Ok(Some(rect)) => rect,
+        Ok(None) => {
+            log::warn("no selection saved yet, --last has nothing to restore");
+            return None;
+        }
+        Err(e) => {
+            log::warn(&format!("--last: {e}"));
+            return None;
+        }
+    };
+
+    onto_overlay(render_window, rect, "the last selection")
+}
+
+// a rect in root coordinates, the way sleek prints them, moved onto the
+// overlay and cut down to what it covers
+fn onto_overlay(render_window: &RenderWindow, rect: Rect, what: &str) -> Option<Rect> {
+    let origin = render_window.origin;
+    let moved = Rect::new(
+        rect.x - origin.x,
+        rect.y - origin.y,
+        rect.width,
+        rect.height,
+    );
+    let screen = Rect::new(
+        0,
+        0,
+        render_window.screen_data.width,
+        render_window.screen_data.height,
+    );
+    let Some(fitted) = moved.intersect(&screen) else {
+        log::warn(&format!("{what} is off screen, ignoring it"));
+        return None;
+    };
+    if (fitted.width, fitted.height) != (moved.width, moved.height) {
+        log::warn(&format!("{what} doesn't fit on screen, clamped it"));
+    }
+    Some(fitted)
+}
+
+// "-" reads the geometry from the first line of stdin
+fn read_geometry(geometry: &str) -> Result<Rect, String> {
+    if geometry != "-" {
+        return geometry.parse();
+    }
+
+    let mut line = String::new();
+    std::io::stdin()
+        .read_line(&mut line)
+        .map_err(|e| e.to_string())?;
+    line.parse()
+}
+
+// how the interactive selection ended, None when it was abandoned with ESC
+enum Selection {
+    Fullscreen,
+    Region(Point, Point),
+}
+
+fn handle_events(
+    render_window: &mut RenderWindow,
+    args: &Args,
+    mut initial: Option<Rect>,
+) -> Result<(), SleekError> {
+    let mut saved = 0;
+    let mut events = events::Stream::new(args.emit_events, render_window.origin, frame_time(args));
+    loop {
+        match select(render_window, args, initial.take(), &mut events) {
+            None => return Ok(()),
+            Some(Selection::Fullscreen) => {
+                events.commit(Rect::new(
+                    0,
+                    0,
+                    render_window.screen_data.width,
+                    render_window.screen_data.height,
+                ));
+                save_fullscreen(render_window, args)?
+            }
+            Some(Selection::Region(point_one, point_two)) => {
+                events.commit(rect_between(point_one, point_two));
+                if args.monitor_region {
+                    return watch::watch_region(render_window, args, point_one, point_two);
+                }
+                save_selection(render_window, args, point_one, point_two)?
+            }
+        }
+
+        // ESC is the only way out of --continuous without --count
+        saved += 1;
+        if !args.continuous || args.count.is_some_and(|count| saved >= count) {
+            return Ok(());
+        }
+        render_window.refreeze()?;
+    }
+}
+
+fn select(
+    render_window: &mut RenderWindow,
+    args: &Args,
+    initial: Option<Rect>,
+    events: &mut events::Stream,
+) -> Option<Selection> {
+    let mut point_one = Point::new(0, 0);
+    let mut point_two = Point::new(0, 0);
+    let mut selection = SelectionState::NotCreated;
+    let mut last_update: std::time::Instant = std::time::Instant::now();
+    let frame_time = frame_time(args);
+    // motion only moves the corner, the selection is redrawn once per frame
+    let mut moved = false;
+    // where the redaction being dragged started
+    let mut redacting: Option<Point> = None;
+
+    // a region proposed by a frontend starts out selected so it can be confirmed
+    // straight away or redrawn
+    let screen = Rect::new(
+        0,
+        0,
+        render_window.screen_data.width,
+        render_window.screen_data.height,
+    );
+    if let Some(rect) = initial.and_then(|rect| rect.intersect(&screen)) {
+        (point_one, point_two) = rect.corners();
+        selection = SelectionState::Selected;
+        draw_selection(render_window, args, point_one, point_two);
+    } else if !args.minimal {
+        hud::draw_status(render_window, args);
+        render_window.present();
+    }
+
+    loop {
+        let current = match selection {
+            SelectionState::NotCreated => None,
+            _ => Some(rect_between(point_one, point_two)),
+        };
+        events.update(render_window.pointer, current);
+
+        // the loupe and crosshair follow the pointer whether or not anything
+        // is selected
+        let mut redraw = moved
+            && (follows_pointer(args)
+                || redacting.is_some()
+                || matches!(selection, SelectionState::Selecting));
+        if redraw && last_update.elapsed() >= frame_time {
+            match selection {
+                SelectionState::NotCreated => clear_selection(render_window, args),
+                _ => draw_selection(render_window, args, point_one, point_two),
+            }
+            last_update = std::time::Instant::now();
+            moved = false;
+            redraw = false;
+        }
+
+        // only wakes up without an event when a redraw is waiting for the next
+        // frame, any number of motion events in between collapse into it. an
+        // idle overlay just blocks
+        let until_frame = redraw.then(|| frame_time.saturating_sub(last_update.elapsed()));
+        let until_event = events.due(render_window.pointer, current);
+        let timeout = match (until_frame, until_event) {
+            (Some(frame), Some(event)) => Some(frame.min(event)),
+            (frame, event) => frame.or(event),
+        };
+        x11_safe::wait_for_event(render_window.display, timeout);
+        if x11_safe::pending(render_window.display) == 0 {
+            continue;
+        }
+
+        match x11_safe::next_event(render_window.display) {
+            Event::Motion { x, y } => {
+                render_window.pointer = Point::new(x, y);
+                moved |= follows_pointer(args);
+                if let Some(start) = redacting {
+                    if let Some(last) = render_window.redactions.last_mut() {
+                        *last = rect_between(start, Point::new(x, y));
+                    }
+                    moved = true;
+                }
+                if let SelectionState::Selecting = selection {
+                    // the grab is confined to the root, not the overlay, so
+                    // under --monitor the pointer can be well outside of it
+                    let point = snap_point(render_window, args, Point::new(x, y));
+                    let point = clamp_to_screen(render_window, point);
+                    let point = lock_ratio(render_window, args, point_one, point);
+                    if point != point_two {
+                        point_two = point;
+                        moved = true;
+                    }
+                }
+            }
+            // with Shift, a drag over a finished selection marks an area to blur
+            Event::ButtonPress {
+                button,
+                x,
+                y,
+                state,
+            } if button == Button1
+                && state & ShiftMask != 0
+                && matches!(selection, SelectionState::Selected) =>
+            {
+                redacting = Some(Point::new(x, y));
+                render_window.redactions.push(Rect::new(x, y, 0, 0));
+            }
+            Event::ButtonRelease { button, x, y } if button == Button1 && redacting.is_some() => {
+                let rect = rect_between(redacting.take().unwrap(), Point::new(x, y));
+                render_window.redactions.pop();
+                if rect.width > 0 && rect.height > 0 {
+                    render_window.redactions.push(rect);
+                }
+                draw_selection(render_window, args, point_one, point_two);
+            }
+            // pressing again over a finished selection starts over, the old
+            // box goes now rather than on the first motion of the new drag
+            Event::ButtonPress { button, x, y, .. } if button == Button1 => {
+                point_one = Point::new(x, y);
+                point_two = Point::new(x, y);
+                render_window.redactions.clear();
+                if let SelectionState::Selected = selection {
+                    clear_selection(render_window, args);
+                }
+                selection = SelectionState::Selecting;
+            }
+            // a drag cancelled with Button3 still ends with a Button1 release
+            Event::ButtonRelease { button, x, y }
+                if button == Button1 && matches!(selection, SelectionState::Selecting) =>
+            {
+                point_two = snap_point(render_window, args, Point::new(x, y));
+                point_two = clamp_to_screen(render_window, point_two);
+                point_two = lock_ratio(render_window, args, point_one, point_two);
+
+                // a click without a drag, there's nothing to capture
+                if too_small(args, point_one, point_two) {
+                    selection = SelectionState::NotCreated;
+                    discard_small(render_window, args);
+                    continue;
+                }
+                draw_selection(render_window, args, point_one, point_two);
+                selection = SelectionState::Selected;
+            }
+            Event::ButtonPress { button, .. }
+                if button == Button3 && matches!(selection, SelectionState::Selecting) =>
+            {
+                point_one = Point::new(0, 0);
+                point_two = Point::new(0, 0);
+                selection = SelectionState::NotCreated;
+                clear_selection(render_window, args);
+            }
+            // arrows resize from the dragged corner, with Ctrl they move the
+            // whole selection. Shift steps 10px instead of 1
+            Event::KeyPress { keysym, state, .. }
+                if matches!(selection, SelectionState::Selected)
+                    && arrow_direction(keysym).is_some() =>
+            {
+                let (dx, dy) = arrow_direction(keysym).unwrap();
+                let step = if state & ShiftMask != 0 { 10 } else { 1 };
+                let (dx, dy) = (dx * step, dy * step);
+
+                if state & ControlMask != 0 {
+                    (point_one, point_two) =
+                        translate_selection(render_window, point_one, point_two, dx, dy);
+                } else {
+                    point_two = clamp_to_screen(
+                        render_window,
+                        Point::new(point_two.x + dx, point_two.y + dy),
+                    );
+                }
+                draw_selection(render_window, args, point_one, point_two);
+            }
+            Event::KeyPress { keysym, .. } => {
+                if args.is_cancel(keysym) {
+                    // the first press only throws away the selection, quitting takes a second
+                    if let SelectionState::NotCreated = selection {
+                        return None;
+                    }
+                    selection = SelectionState::NotCreated;
+                    render_window.redactions.clear();
+                    redacting = None;
+                    clear_selection(render_window, args);
+                } else if args.is_fullscreen(keysym) {
+                    // skips clearing a half drawn selection just to get at the whole screen
+                    return Some(Selection::Fullscreen);
+                } else if args.is_confirm(keysym) {
+                    // the arrow keys can shrink a selection down to nothing
+                    if matches!(selection, SelectionState::Selected)
+                        && too_small(args, point_one, point_two)
+                    {
+                        selection = SelectionState::NotCreated;
+                        discard_small(render_window, args);
+                        continue;
+                    }
+                    return Some(match selection {
+                        SelectionState::NotCreated => Selection::Fullscreen,
+                        _ => Selection::Region(point_one, point_two),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn frame_time(args: &Args) -> std::time::Duration {
+    std::time::Duration::from_nanos(1_000_000_000 / args.refresh_rate as u64)
+}
+
+fn rect_between(point_one: Point, point_two: Point) -> Rect {
+    let (min, max) = (point_one.min(&point_two), point_one.max(&point_two));
+    Rect::new(min.x, min.y, max.x - min.x, max.y - min.y)
+}
+
+fn too_small(args: &Args, point_one: Point, point_two: Point) -> bool {
+    (point_two.x - point_one.x).abs() < args.min_size
+        || (point_two.y - point_one.y).abs() < args.min_size
+}
+
+// the overlay stays up for another try
+fn discard_small(render_window: &mut RenderWindow, args: &Args) {
+    log::warn(&format!(
+        "selection smaller than {0}x{0}, nothing captured",
+        args.min_size
+    ));
+    clear_selection(render_window, args);
+}
+
+fn arrow_direction(keysym: u64) -> Option<(i32, i32)> {
+    match keysym as u32 {
+        x11::keysym::XK_Left => Some((-1, 0)),
+        x11::keysym::XK_Right => Some((1, 0)),
+        x11::keysym::XK_Up => Some((0, -1)),
+        x11::keysym::XK_Down => Some((0, 1)),
+        _ => None,
+    }
+}
+
+fn clamp_to_screen(render_window: &RenderWindow, point: Point) -> Point {
+    Point::new(
+        point.x.clamp(0, render_window.screen_data.width),
+        point.y.clamp(0, render_window.screen_data.height),
+    )
+}
+
+// the move is cut short at the screen edge so the selection keeps its size
+fn translate_selection(
+    render_window: &RenderWindow,
+    point_one: Point,
+    point_two: Point,
+    dx: i32,
+    dy: i32,
+) -> (Point, Point) {
+    let min = point_one.min(&point_two);
+    let max = point_one.max(&point_two);
+    // a selection wider than the screen can't keep to both edges, the left
+    // and top ones win. clamp would panic on it
+    let dx = dx
+        .max(-min.x)
+        .min((render_window.screen_data.width - max.x).max(-min.x));
+    let dy = dy
+        .max(-min.y)
+        .min((render_window.screen_data.height - max.y).max(-min.y));
+
+    (
+        Point::new(point_one.x + dx, point_one.y + dy),
+        Point::new(point_two.x + dx, point_two.y + dy),
+    )
+}
+
+// only the dragged corner is snapped, the anchor stays where it was pressed
+fn snap_point(render_window: &RenderWindow, args: &Args, point: Point) -> Point {
+    if !args.snap {
+        return point;
+    }
+
+    let origin = render_window.origin;
+    let snapped = window::snap(
+        &render_window.windows,
+        Point::new(point.x + origin.x, point.y + origin.y),
+        args.snap_threshold,
+    );
+    Point::new(snapped.x - origin.x, snapped.y - origin.y)
+}
+
+fn lock_ratio(render_window: &RenderWindow, args: &Args, anchor: Point, point: Point) -> Point {
+    let Some(ratio) = args.ratio else {
+        return point;
+    };
+    fit_ratio(
+        anchor,
+        point,
+        ratio,
+        render_window.screen_data.width,
+        render_window.screen_data.height,
+    )
+}
+
+// grows the selection along whichever axis was dragged further (relative to
+// the ratio), in whole steps of the ratio so it's exact, without leaving the
+// width x height screen
+fn fit_ratio(anchor: Point, point: Point, ratio: Ratio, width: i32, height: i32) -> Point {
+    let (dx, dy) = (point.x - anchor.x, point.y - anchor.y);
+    let room_x = if dx < 0 { anchor.x } else { width - anchor.x };
+    let room_y = if dy < 0 { anchor.y } else { height - anchor.y };
+
+    let steps = (dx.abs() / ratio.width)
+        .max(dy.abs() / ratio.height)
+        .min(room_x / ratio.width)
+        .min(room_y / ratio.height);
+
+    let sign = |delta: i32| if delta < 0 { -1 } else { 1 };
+    Point::new(
+        anchor.x + sign(dx) * steps * ratio.width,
+        anchor.y + sign(dy) * steps * ratio.height,
+    )
+}
+
+fn clear_selection(render_window: &mut RenderWindow, args: &Args) {
+    render_window.reset();
+    if args.crosshair {
+        hud::draw_crosshair(render_window);
+    }
+    if !args.minimal {
+        hud::draw_status(render_window, args);
+    }
+    if args.loupe {
+        loupe::draw(render_window, args);
+    }
+    render_window.present();
+}
+
+// overlays that need redrawing on every pointer move, selecting or not
+fn follows_pointer(args: &Args) -> bool {
+    args.loupe || args.crosshair
+}
+
+fn draw_selection(
+    render_window: &mut RenderWindow,
+    args: &Args,
+    point_one: Point,
+    point_two: Point,
+) {
+    let min = point_one.min(&point_two);
+    let max = point_one.max(&point_two);
+
+    let width = max.x - min.x;
+    let height = max.y - min.y;
+    let selection = Rect::new(min.x, min.y, width, height);
+
+    // only what was painted last frame goes back to the background, redoing
+    // the whole screen every motion event is slow on big displays. with
+    // dimming the new selection is copied over anyway so it's skipped
+    let previous = std::mem::take(&mut render_window.drawn);
+    for rect in previous {
+        let stale = match render_window.dimmed {
+            Some(_) => rect.subtract(&selection),
+            None => vec![rect],
+        };
+        for rect in stale {
+            render_window.restore(rect);
+        }
+    }
+
+    // undo the dimming inside the selection so it shows exactly what will be saved
+    if render_window.dimmed.is_some() {
+        render_window.copy_to_buffer(render_window.pixmap, selection);
+    }
+
+    let radius = corner_radius(args);
+    let outline_depth = radius.map_or(0, |radius| radius.min(width / 2).min(height / 2));
+    if args.shape == Shape::Ellipse {
+        // the stroke cuts through the middle of every edge strip
+        let outer = line_width(args, render_window.scale) / 2 + 1;
+        render_window.mark_drawn(Rect::new(
+            min.x - outer,
+            min.y - outer,
+            width + 2 * outer,
+            height + 2 * outer,
+        ));
+    } else {
+        for band in outline_bands(
+            selection,
+            line_width(args, render_window.scale),
+            outline_depth,
+        ) {
+            render_window.mark_drawn(band);
+        }
+    }
+
+    if let Some(divisions) = args.grid {
+        hud::draw_grid(render_window, selection, divisions);
+    }
+
+    if args.shape == Shape::Ellipse {
+        x11_safe::draw_arc(
+            render_window.display,
+            render_window.buffer,
+            render_window.graphics_context,
+            min.x,
+            min.y,
+            width as u32,
+            height as u32,
+            0,
+            360 * 64,
+        );
+    } else if let Some(radius) = radius {
+        draw_rounded_rectangle(render_window, min, width, height, radius);
+    } else {
+        x11_safe::draw_rectangle(
+            render_window.display,
+            render_window.buffer,
+            render_window.graphics_context,
+            min.x,
+            min.y,
+            width as u32,
+            height as u32,
+        );
+    }
+
+    for rect in render_window.redactions.clone() {
+        hud::draw_redaction(render_window, rect);
+    }
+
+    if args.crosshair {
+        hud::draw_crosshair(render_window);
+    }
+
+    // --minimal leaves the outline as the only thing drawn over the screen
+    if !args.minimal {
+        hud::draw_dimensions(render_window, args, min, max, point_two);
+        hud::draw_status(render_window, args);
+    }
+    if args.loupe {
+        loupe::draw(render_window, args);
+    }
+
+    render_window.present();
+}
+
+// four strips covering the stroke along each edge, reaching depth further in
+// for the rounded corners
+fn outline_bands(selection: Rect, line_width: i32, depth: i32) -> [Rect; 4] {
+    let outer = line_width / 2 + 1;
+    let inner = outer.max(depth + 1);
+    let Rect {
+        x,
+        y,
+        width,
+        height,
+    } = selection;
+
+    [
+        Rect::new(x - outer, y - outer, width + 2 * outer, outer + inner),
+        Rect::new(
+            x - outer,
+            y + height - inner,
+            width + 2 * outer,
+            outer + inner,
+        ),
+        Rect::new(x - outer, y - outer, outer + inner, height + 2 * outer),
+        Rect::new(
+            x + width - inner,
+            y - outer,
+            outer + inner,
+            height + 2 * outer,
+        ),
+    ]
+}
+
+// --radius also cuts the corners out of the capture, --overlay-rounded only
+// rounds the preview
+fn corner_radius(args: &Args) -> Option<i32> {
+    args.radius
+        .or(args.overlay_rounded.then_some(OVERLAY_RADIUS))
+}
+
+// the radius is clamped to half the shorter side
+fn draw_rounded_rectangle(
+    render_window: &mut RenderWindow,
+    min: Point,
+    width: i32,
+    height: i32,
+    radius: i32,
+) {
+    let r = radius.min(width / 2).min(height / 2);
+    let d = (2 * r) as u32;
+    let (x, y) = (min.x, min.y);
+
+    let display = render_window.display;
+    let window = render_window.buffer;
+    let gc = render_window.graphics_context;
+
+    // angles are in 64ths of a degree, counter-clockwise from 3 o'clock
+    x11_safe::draw_arc(display, window, gc, x, y, d, d, 90 * 64, 90 * 64);
+    x11_safe::draw_arc(display, window, gc, x + width - 2 * r, y, d, d, 0, 90 * 64);
+    x11_safe::draw_arc(
+        display,
+        window,
+        gc,
+        x,
+        y + height - 2 * r,
+        d,
+        d,
+        180 * 64,
+        90 * 64,
+    );
+    x11_safe::draw_arc(
+        display,
+        window,
+        gc,
+        x + width - 2 * r,
+        y + height - 2 * r,
+        d,
+        d,
+        270 * 64,
+        90 * 64,
+    );
+
+    x11_safe::draw_line(display, window, gc, x + r, y, x + width - r, y);
+    x11_safe::draw_line(
+        display,
+        window,
+        gc,
+        x + r,
+        y + height,
+        x + width - r,
+        y + height,
+    );
+    x11_safe::draw_line(display, window, gc, x, y + r, x, y + height - r);
+    x11_safe::draw_line(
+        display,
+        window,
+        gc,
+        x + width,
+        y + r,
+        x + width,
+        y + height - r,
+    );
+}
+
+fn save_fullscreen(render_window: &mut RenderWindow, args: &Args) -> Result<(), SleekError> {
+    let size = Point::new(
+        render_window.screen_data.width,
+        render_window.screen_data.height,
+    );
+    save_selection(render_window, args, Point::new(0, 0), size)
+}
+
+fn save_selection(
+    render_window: &mut RenderWindow,
+    args: &Args,
+    point_one: Point,
+    point_two: Point,
+) -> Result<(), SleekError> {
+    // a region handed in from outside (--active, --relative-to-active) can
+    // hang off the screen, and XGetImage refuses anything outside the drawable
+    let min = clamp_to_screen(render_window, point_one.min(&point_two));
+    let max = clamp_to_screen(render_window, point_one.max(&point_two));
+    if min.x == max.x || min.y == max.y {
+        return Err(SleekError::Capture(
+            "the selection is entirely off screen".to_owned(),
+        ));
+    }
+
+    let origin = render_window.origin;
+    let rect = Rect::new(
+        min.x + origin.x,
+        min.y + origin.y,
+        max.x - min.x,
+        max.y - min.y,
+    );
+    // not being able to remember it only matters to a later --last
+    if let Err(e) = state::save_last(rect) {
+        log::info(&format!("could not remember the selection for --last: {e}"));
+    }
+
+    if let Some(format) = &args.print_geometry {
+        println!("{}", format_geometry(format, rect));
+        return Ok(());
+    }
+
+    let filepath = output_path(args)?;
+
+    render_window.clear();
+
+    // with --no-freeze the pixmap is whatever was on screen at startup, so
+    // grab again now that the outline is gone. the apps underneath need a
+    // moment to redraw the strips the outline was covering
+    if render_window.live {
+        x11_safe::sync(render_window.display);
+        std::thread::sleep(SETTLE_TIME);
+        render_window.snapshot()?;
+    }
+
+    if args.selection_outline_only {
+        let mut screen = grab(
+            render_window,
+            render_window.pixmap,
+            Point::new(0, 0),
+            Point::new(
+                render_window.screen_data.width,
+                render_window.screen_data.height,
+            ),
+        )?;
+        draw_cursor(render_window, &mut screen, Point::new(0, 0));
+        annotate::draw_outline(
+            &mut screen,
+            min,
+            max,
+            args.line_colour,
+            line_width(args, render_window.scale),
+        );
+        let screen_rect = Rect::new(0, 0, screen.width() as i32, screen.height() as i32);
+
+        if !args.also_crop {
+            return write_capture(render_window, args, &filepath, screen.into(), screen_rect);
+        }
+        let outline_path = suffixed_path(&filepath, "outline");
+        write_capture(
+            render_window,
+            args,
+            &outline_path,
+            screen.into(),
+            screen_rect,
+        )?;
+    }
+
+    let region = Rect::new(min.x, min.y, max.x - min.x, max.y - min.y);
+
+    // the box around the ellipse or rounded rectangle is the image, with
+    // everything outside the outline transparent. icons and the clipboard stay
+    // RGB rectangles
+    let cut_out = args.shape == Shape::Ellipse || args.radius.is_some();
+    if cut_out && args.resolutions.is_empty() && !args.clipboard {
+        let image = if args.alpha && render_window.screen_data.has_alpha() {
+            grab_rgba(render_window, render_window.pixmap, min, max)?
+        } else {
+            image::DynamicImage::from(grab(render_window, render_window.pixmap, min, max)?)
+                .into_rgba8()
+        };
+        let mut image = touch_up(render_window, args, image, min);
+        match args.radius {
+            Some(radius) if args.shape == Shape::Rectangle => {
+                // the radius is in screen pixels, the image may have been scaled since
+                let radius = radius as f64 * image.width() as f64 / (max.x - min.x) as f64;
+                clear_outside_corners(&mut image, radius);
+            }
+            _ => clear_outside_ellipse(&mut image),
+        }
+        return write_capture(render_window, args, &filepath, image.into(), region);
+    }
+
+    // icons and the clipboard stay RGB
+    if args.alpha && args.resolutions.is_empty() && !args.clipboard {
+        if render_window.screen_data.has_alpha() {
+            let image = grab_rgba(render_window, render_window.pixmap, min, max)?;
+            let image = touch_up(render_window, args, image, min);
+            return write_capture(render_window, args, &filepath, image.into(), region);
+        }
+        log::warn("the visual has no alpha channel, saving RGB");
+    }
+
+    // the frozen background rather than the window, which is dimmed outside the selection
+    let image = grab(render_window, render_window.pixmap, min, max)?;
+    let image = touch_up(render_window, args, image, min);
+
+    if !args.resolutions.is_empty() {
+        return write_resolutions(render_window, args, &filepath, &image, region);
+    }
+
+    if args.clipboard {
+        return copy_to_clipboard(render_window, args, &filepath, image, region);
+    }
+
+    write_capture(render_window, args, &filepath, image.into(), region)
+}
+
+// %x %y %w %h are the rect in root coordinates, %% is a literal %
+fn format_geometry(format: &str, rect: Rect) -> String {
+    let mut formatted = String::new();
+    let mut chars = format.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            formatted.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('x') => formatted.push_str(&rect.x.to_string()),
+            Some('y') => formatted.push_str(&rect.y.to_string()),
+            Some('w') => formatted.push_str(&rect.width.to_string()),
+            Some('h') => formatted.push_str(&rect.height.to_string()),
+            Some(other) => {
+                if other != '%' {
+                    formatted.push('%');
+                }
+                formatted.push(other);
+            }
+            None => formatted.push('%'),
+        }
+    }
+    formatted
+}
+
+// everything done to a grabbed selection before it's encoded, in this order
+// so the cursor is never blurred and the rects still line up with the pixels
+fn touch_up<P: image::Pixel<Subpixel = u8> + 'static>(
+    render_window: &RenderWindow,
+    args: &Args,
+    mut image: image::ImageBuffer<P, Vec<u8>>,
+    min: Point,
+) -> image::ImageBuffer<P, Vec<u8>> {
+    let redactions: Vec<Rect> = render_window
+        .redactions
+        .iter()
+        .map(|rect| Rect::new(rect.x - min.x, rect.y - min.y, rect.width, rect.height))
+        .collect();
+    annotate::redact(&mut image, &redactions, args.blur_strength);
+    draw_cursor(render_window, &mut image, min);
+    let mut image = match args.scale {
+        Some(scale) => scale::apply(image, scale, args.filter),
+        None => image,
+    };
+    if args.stamp {
+        stamp(render_window, args, &mut image);
+    }
+    image
+}
+
+// --stamp goes on after scaling so the text stays sharp at any size
+fn stamp<P: image::Pixel<Subpixel = u8>>(
+    render_window: &RenderWindow,
+    args: &Args,
+    image: &mut image::ImageBuffer<P, Vec<u8>>,
+) {
+    let text = Local::now().format(&args.stamp_format).to_string();
+    let Some(mask) = hud::text_mask(render_window, &text) else {
+        log::warn("no font to draw --stamp with, saving without it");
+        return;
+    };
+    annotate::stamp(
+        image,
+        &mask,
+        args.stamp_corner,
+        args.stamp_colour,
+        render_window.scaled(2),
+    );
+}
+
+// min is the image's top left corner on the overlay
+fn draw_cursor<P: image::Pixel<Subpixel = u8>>(
+    render_window: &RenderWindow,
+    image: &mut image::ImageBuffer<P, Vec<u8>>,
+    min: Point,
+) {
+    if let Some(cursor) = &render_window.cursor {
+        let origin = render_window.origin;
+        annotate::draw_cursor(
+            image,
+            cursor,
+            Point::new(origin.x + min.x, origin.y + min.y),
+        );
+    }
+}
+
+// the file is only written as well when an output path was asked for
+fn copy_to_clipboard(
+    render_window: &mut RenderWindow,
+    args: &Args,
+    filepath: &str,
+    image: image::RgbImage,
+    region: Rect,
+) -> Result<(), SleekError> {
+    let mut png = Vec::new();
+    output::write_png(&mut png, &image, &Default::default())
+        .map_err(|e| SleekError::write("clipboard", e))?;
+
+    if args.template.is_some() {
+        write_capture(render_window, args, filepath, image.into(), region)?;
+    }
+
+    // with --copy-path the same owner offers the path as text too
+    let path = args.copy_path.then(take_last_saved).flatten();
+    let text = path.as_ref().map(|path| path.to_string_lossy());
+    let contents = clipboard::Contents {
+        png: Some(&png),
+        text: text.as_deref(),
+    };
+    if let Err(e) = clipboard::serve(render_window, &contents) {
+        eprintln!("--clipboard: {e}");
+    }
+    Ok(())
+}
+
+// icon sets: one NxN copy per requested size, e.g. shot-32x32.png
+fn write_resolutions(
+    render_window: &RenderWindow,
+    args: &Args,
+    filepath: &str,
+    image: &image::RgbImage,
+    region: Rect,
+) -> Result<(), SleekError> {
+    let (width, height) = (image.width() as f64, image.height() as f64);
+    if (width / height - 1.0).abs() > 0.1 {
+        log::warn(&format!(
+            "selection is {width}x{height}, icons will be stretched to a square"
+        ));
+    }
+
+    for (sized_path, resized) in icon_set(filepath, image, &args.resolutions) {
+        write_capture(render_window, args, &sized_path, resized.into(), region)?;
+    }
+    Ok(())
+}
+
+// shot.png at 16 and 32 -> shot-16x16.png and shot-32x32.png
+fn icon_set<'a>(
+    filepath: &'a str,
+    image: &'a image::RgbImage,
+    sizes: &'a [u32],
+) -> impl Iterator<Item = (String, image::RgbImage)> + 'a {
+    sizes.iter().map(move |&size| {
+        let resized =
+            image::imageops::resize(image, size, size, image::imageops::FilterType::Lanczos3);
+        (suffixed_path(filepath, &format!("{size}x{size}")), resized)
+    })
+}
+
+// the shell only expands ~ at the start of an unquoted word, a template is
+// almost always quoted because of the %s and the config file has no shell at all
+fn expand_home(path: &str) -> String {
+    match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => std::path::Path::new(&home)
+            .join(rest)
+            .to_string_lossy()
+            .into_owned(),
+        _ => path.to_owned(),
+    }
+}
+
+fn output_path(args: &Args) -> Result<String, SleekError> {
+    if args.template.as_deref() == Some("-") {
+        return Ok("-".to_owned());
+    }
+
+    let now = Local::now();
+
+    // from the command line or the config, either way chrono would panic
+    // formatting a bad one
+    let template = args
+        .template
+        .as_deref()
+        .unwrap_or("sleek-%Y-%m-%d:%H:%M:%S");
+    args::strftime(template).map_err(|e| SleekError::Template(template.to_owned(), e))?;
+
+    let filepath = format!("{}", now.format(template)).trim().to_owned();
+
+    // the extension picks the format, PNG unless it's one sleek knows
+    let filepath = match output::Format::from_path(&filepath) {
+        Some(_) => filepath,
+        None => filepath + ".png",
+    };
+
+    // an absolute template, ~ included, already says where it goes
+    let filepath = std::path::PathBuf::from(expand_home(&filepath));
+    let filepath = match &args.output_dir {
+        Some(directory) if filepath.is_relative() => {
+            std::path::Path::new(&expand_home(directory)).join(filepath)
+        }
+        _ => filepath,
+    };
+
+    // shots/name.png -> shots/YYYY/MM/DD/name.png
+    let directory = filepath.parent().unwrap_or(std::path::Path::new(""));
+    let directory = if args.date_folders {
+        directory.join(now.format("%Y/%m/%d").to_string())
+    } else {
+        directory.to_path_buf()
+    };
+    // the template can name directories that don't exist yet, %Y/%m included
+    if !directory.as_os_str().is_empty() {
+        std::fs::create_dir_all(&directory)
+            .map_err(|e| SleekError::write(&directory.to_string_lossy(), e))?;
+    }
+
+    // a template like "shots/" or ".." has nothing to name the file
+    let Some(file_name) = filepath.file_name() else {
+        return Err(SleekError::write(
+            &filepath.to_string_lossy(),
+            "the template doesn't end in a file name",
+        ));
+    };
+
+    let filepath = directory.join(file_name).to_string_lossy().into_owned();
+
+    // --continuous can save several times a second, which the default
+    // template can't tell apart
+    if args.continuous && std::path::Path::new(&filepath).exists() {
+        return Ok((2..)
+            .map(|n| suffixed_path(&filepath, &n.to_string()))
+            .find(|path| !std::path::Path::new(path).exists())
+            .unwrap());
+    }
+    Ok(filepath)
+}
+
+fn write_window_capture(
+    args: &Args,
+    filepath: &str,
+    capture: window::Capture,
+) -> Result<(), SleekError> {
+    let image = capture.image;
+
+    if let Some(name) = &args.shm_name {
+        return write_shm(name, &image.into_rgba8());
+    }
+
+    let metadata = sidecar::Metadata {
+        geometry: capture.geometry,
+        monitor: capture.monitor,
+        window: capture.info,
+    };
+    save_image(
+        args,
+        filepath,
+        &image,
+        &png_chunks(args, filepath, &metadata),
+    )?;
+
+    if args.sidecar && filepath != "-" {
+        sidecar::write(filepath, &metadata)
+            .map_err(|e| SleekError::write(&sidecar::sidecar_path(filepath), e))?;
+    }
+    Ok(())
+}
+
+// --metadata is opt in so the same pixels always encode to the same bytes
+fn png_chunks(args: &Args, filepath: &str, metadata: &sidecar::Metadata) -> output::PngChunks {
+    let format = output::Format::from_path(filepath).unwrap_or(output::Format::Png);
+    output::PngChunks {
+        orientation: None,
+        text: if args.metadata && format == output::Format::Png {
+            sidecar::png_text(metadata)
+        } else {
+            Vec::new()
+        },
+    }
+}
+
+// the most recent file written, for --copy-path
+static LAST_SAVED: std::sync::Mutex<Option<std::path::PathBuf>> = std::sync::Mutex::new(None);
+
+fn take_last_saved() -> Option<std::path::PathBuf> {
+    LAST_SAVED.lock().unwrap_or_else(|e| e.into_inner()).take()
+}
+
+// the absolute path of every file written is the only thing printed to stdout,
+// so `sleek | xargs feh` and the like just work. an output path of - streams
+// the image itself to stdout instead
+fn save_image(
+    args: &Args,
+    filepath: &str,
+    image: &image::DynamicImage,
+    chunks: &output::PngChunks,
+) -> Result<(), SleekError> {
+    let to_stdout = filepath == "-";
+    let format = output::Format::from_path(filepath).unwrap_or(output::Format::Png);
+
+    let result = if to_stdout {
+        let mut stdout = std::io::BufWriter::new(std::io::stdout().lock());
+        // only for whatever reads the pipe, a file on disk has to stay a valid image
+        let header = if args.stream_header {
+            output::write_stream_header(&mut stdout, format.name(), image.width(), image.height())
+                .map_err(Into::into)
+        } else {
+            Ok(())
+        };
+        header.and_then(|()| write_encoded(args, &mut stdout, format, image, chunks))
+    } else {
+        write_atomically(filepath, |file| {
+            write_encoded(args, file, format, image, chunks)
+        })
+    };
+    result.map_err(|e| SleekError::write(filepath, e))?;
+
+    if !to_stdout {
+        let path = std::fs::canonicalize(filepath).unwrap_or_else(|_| filepath.into());
+        log::info(&format!("wrote {} as {}", path.display(), format.name()));
+        log::saved(&path);
+        *LAST_SAVED.lock().unwrap_or_else(|e| e.into_inner()) = Some(path.clone());
+
+        if args.notify {
+            hook::notify(&path.to_string_lossy());
+        }
+        if let Some(command) = &args.exec {
+            hook::run(args, command, &path.to_string_lossy());
+        }
+    }
+    Ok(())
+}
+
+// written into a hidden file next to the target and only renamed over it once
+// it's complete and on disk, so the path never holds a partial file and a
+// directory watcher sees one finished file appear
+pub(crate) fn write_atomically(
+    filepath: &str,
+    write: impl FnOnce(&mut dyn std::io::Write) -> Result<(), Box<dyn std::error::Error>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let path = std::path::Path::new(filepath);
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temporary = path.with_file_name(format!(".{name}.sleek-tmp{}", std::process::id()));
+
+    let result = std::fs::File::create(&temporary)
+        .map_err(Into::into)
+        .and_then(|file| {
+            let mut writer = std::io::BufWriter::new(file);
+            write(&mut writer)?;
+            writer.get_ref().sync_all()?;
+            Ok(())
+        })
+        .and_then(|()| Ok(std::fs::rename(&temporary, path)?));
+
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temporary);
+    }
+    result
+}
+
+fn write_encoded(
+    args: &Args,
+    writer: &mut dyn std::io::Write,
+    format: output::Format,
+    image: &image::DynamicImage,
+    chunks: &output::PngChunks,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let jpeg = output::JpegOptions {
+        quality: args.quality,
+        subsampling: args.jpeg_subsampling,
+        progressive: args.jpeg_progressive,
+    };
+    output::write_image(&mut *writer, format, image, chunks, jpeg)?;
+    writer.flush()?;
+    Ok(())
+}
+
+// the line on stdout is all a frontend needs to map the segment
+fn write_shm(name: &str, image: &image::RgbaImage) -> Result<(), SleekError> {
+    let name = shm::write_rgba(name, image.width(), image.height(), image.as_raw())
+        .map_err(SleekError::Shm)?;
+    println!("{name} {} {} rgba8", image.width(), image.height());
+    Ok(())
+}
+
+// shot.png -> shot-suffix.png, keeping whichever extension picked the format
+fn suffixed_path(filepath: &str, suffix: &str) -> String {
+    match filepath.rsplit_once('.') {
+        Some((stem, extension)) if output::Format::from_path(filepath).is_some() => {
+            format!("{stem}-{suffix}.{extension}")
+        }
+        _ => format!("{filepath}-{suffix}.png"),
+    }
+}
+
+fn grab(
+    render_window: &RenderWindow,
+    drawable: u64,
+    min: Point,
+    max: Point,
+) -> Result<image::RgbImage, SleekError> {
+    let width = max.x - min.x;
+    let height = max.y - min.y;
+    log::info(&format!("grabbing {width}x{height} at {},{}", min.x, min.y));
+
+    let image = x11_safe::get_image(
+        render_window.display,
+        drawable,
+        min.x,
+        min.y,
+        width as u32,
+        height as u32,
+    )?;
+
+    let pixels: Vec<u8> = image
+        .pixels()
+        .iter()
+        .flat_map(|p| {
+            let colour = render_window.screen_data.decode(*p);
+            [colour.r, colour.g, colour.b]
+        })
+        .collect();
+
+    Ok(image::RgbImage::from_raw(width as u32, height as u32, pixels).unwrap())
+}
+
+fn grab_rgba(
+    render_window: &RenderWindow,
+    drawable: u64,
+    min: Point,
+    max: Point,
+) -> Result<image::RgbaImage, SleekError> {
+    let width = max.x - min.x;
+    let height = max.y - min.y;
+    log::info(&format!("grabbing {width}x{height} at {},{}", min.x, min.y));
+
+    let image = x11_safe::get_image(
+        render_window.display,
+        drawable,
+        min.x,
+        min.y,
+        width as u32,
+        height as u32,
+    )?;
+
+    let pixels: Vec<u8> = image
+        .pixels()
+        .iter()
+        .flat_map(|p| render_window.screen_data.decode_rgba(*p))
+        .collect();
+
+    Ok(image::RgbaImage::from_raw(width as u32, height as u32, pixels).unwrap())
+}
+
+// tested at each pixel's centre against the ellipse inscribed in the image
+fn clear_outside_ellipse(image: &mut image::RgbaImage) {
+    let (a, b) = (image.width() as f64 / 2.0, image.height() as f64 / 2.0);
+    for (x, y, pixel) in image.enumerate_pixels_mut() {
+        let dx = (x as f64 + 0.5 - a) / a;
+        let dy = (y as f64 + 0.5 - b) / b;
+        if dx * dx + dy * dy > 1.0 {
+            pixel.0 = [0, 0, 0, 0];
+        }
+    }
+}
+
+// the same test against a quarter circle in each corner
+fn clear_outside_corners(image: &mut image::RgbaImage, radius: f64) {
+    let (width, height) = (image.width() as f64, image.height() as f64);
+    let radius = radius.min(width / 2.0).min(height / 2.0);
+    for (x, y, pixel) in image.enumerate_pixels_mut() {
+        let (x, y) = (x as f64 + 0.5, y as f64 + 0.5);
+        let dx = (radius - x).max(x - (width - radius)).max(0.0);
+        let dy = (radius - y).max(y - (height - radius)).max(0.0);
+        if dx * dx + dy * dy > radius * radius {
+            pixel.0 = [0, 0, 0, 0];
+        }
+    }
+}
+
+fn write_capture(
+    render_window: &RenderWindow,
+    args: &Args,
+    filepath: &str,
+    mut image: image::DynamicImage,
+    region: Rect,
+) -> Result<(), SleekError> {
+    let origin = render_window.origin;
+    let region = Rect::new(
+        region.x + origin.x,
+        region.y + origin.y,
+        region.width,
+        region.height,
+    );
+    let centre = Point::new(region.x + region.width / 2, region.y + region.height / 2);
+    let monitor = monitor::monitor_at(render_window.display, render_window.root, centre);
+
+    if let Some(name) = &args.shm_name {
+        return write_shm(name, &image.into_rgba8());
+    }
+
+    let metadata = sidecar::Metadata {
+        geometry: region,
+        monitor,
+        window: None,
+    };
+
+    // only PNG gets an eXIf chunk, everything else keeps the pixels as they are on screen
+    let mut chunks = png_chunks(args, filepath, &metadata);
+    let format = output::Format::from_path(filepath).unwrap_or(output::Format::Png);
+    if args.rotation_mode == RotationMode::Tag && format == output::Format::Png {
+        if let Some(monitor) = monitor {
+            if rotation::exif_orientation(monitor.rotation) != 1 {
+                chunks.orientation = Some(rotation::exif_orientation(monitor.rotation));
+                image = rotation::unrotate(image, monitor.rotation);
+            }
+        }
+    }
+
+    save_image(args, filepath, &image, &chunks)?;
+
+    if args.sidecar && filepath != "-" {
+        sidecar::write(filepath, &metadata)
+            .map_err(|e| SleekError::write(&sidecar::sidecar_path(filepath), e))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_hex_colours() {
+        let colour: RGB = "#ff8000".parse().unwrap();
+        assert_eq!((colour.r, colour.g, colour.b), (0xff, 0x80, 0x00));
+        let colour: RGB = "0a0B0c".parse().unwrap();
+        assert_eq!((colour.r, colour.g, colour.b), (0x0a, 0x0b, 0x0c));
+    }
+
+    #[test]
+    fn rejects_malformed_colours() {
+        for colour in ["", "#", "fff", "#ff80000", "ff800", "#gg0000", "ff 800"] {
+            assert!(colour.parse::<RGB>().is_err(), "{colour:?} parsed");
+        }
+    }
+
+    #[test]
+    fn packs_888_visuals() {
+        let screen = ScreenData::new(0, 0, 24, 0xff0000, 0x00ff00, 0x0000ff);
+        assert_eq!(screen.encode(RGB::new(0x12, 0x34, 0x56)), 0x123456);
+
+        // the alpha bits of a 32 bit visual are always opaque
+        let screen = ScreenData::new(0, 0, 32, 0xff0000, 0x00ff00, 0x0000ff);
+        assert_eq!(screen.encode(RGB::new(0x12, 0x34, 0x56)), 0xff123456);
+    }
+
+    #[test]
+    fn packs_565_and_bgr_visuals() {
+        let screen = ScreenData::new(0, 0, 16, 0xf800, 0x07e0, 0x001f);
+        assert_eq!(screen.encode(RGB::new(255, 255, 255)), 0xffff);
+        assert_eq!(screen.encode(RGB::new(255, 0, 0)), 0xf800);
+        assert_eq!(screen.encode(RGB::new(0, 0, 0)), 0);
+
+        let screen = ScreenData::new(0, 0, 24, 0x0000ff, 0x00ff00, 0xff0000);
+        assert_eq!(screen.encode(RGB::new(0x12, 0x34, 0x56)), 0x563412);
+    }
+
+    #[test]
+    fn line_width_scales_and_caps() {
+        let mut args = Args::default();
+        assert_eq!(line_width(&args, 1.0), LINE_WIDTH);
+        assert_eq!(line_width(&args, 2.0), LINE_WIDTH * 2);
+        // a very dense display can't push the default past the cap
+        assert_eq!(line_width(&args, 100.0), MAX_LINE_WIDTH);
+
+        // a width that was asked for is used as it is
+        args.line_width = Some(1);
+        assert_eq!(line_width(&args, 3.0), 1);
+        args.line_width = Some(MAX_LINE_WIDTH);
+        assert_eq!(line_width(&args, 3.0), MAX_LINE_WIDTH);
+    }
+
+    #[test]
+    fn ratios_are_reduced() {
+        let ratio: Ratio = "16:9".parse().unwrap();
+        assert_eq!((ratio.width, ratio.height), (16, 9));
+        let ratio: Ratio = "1920:1080".parse().unwrap();
+        assert_eq!((ratio.width, ratio.height), (16, 9));
+        let ratio: Ratio = " 4 : 4 ".parse().unwrap();
+        assert_eq!((ratio.width, ratio.height), (1, 1));
+    }
+
+    #[test]
+    fn rejects_bad_ratios() {
+        for ratio in ["0:1", "1:0", "-4:3", "a:b", "16", "16:", ":9", "16/9"] {
+            assert!(ratio.parse::<Ratio>().is_err(), "{ratio:?} parsed");
+        }
+    }
+
+    #[test]
+    fn ratio_locks_to_whole_steps_in_every_direction() {
+        let ratio: Ratio = "4:3".parse().unwrap();
+        let anchor = Point::new(500, 500);
+        // the axis dragged further picks the number of steps
+        let cases = [
+            (Point::new(541, 510), Point::new(540, 530)),
+            (Point::new(459, 510), Point::new(460, 530)),
+            (Point::new(541, 490), Point::new(540, 470)),
+            (Point::new(459, 490), Point::new(460, 470)),
+            (Point::new(505, 431), Point::new(592, 431)),
+        ];
+        for (dragged, locked) in cases {
+            let point = fit_ratio(anchor, dragged, ratio, 1000, 1000);
+            assert_eq!(point, locked, "dragged to {dragged:?}");
+            let (w, h) = ((point.x - anchor.x).abs(), (point.y - anchor.y).abs());
+            assert_eq!(w * 3, h * 4);
+        }
+    }
+
+    #[test]
+    fn ratio_stops_at_the_screen_edge() {
+        let ratio: Ratio = "2:1".parse().unwrap();
+        // 30px of room to the right allows 15 steps, even though the drag asks for more
+        let point = fit_ratio(
+            Point::new(970, 100),
+            Point::new(1200, 150),
+            ratio,
+            1000,
+            1000,
+        );
+        assert_eq!(point, Point::new(1000, 115));
+        // the vertical room can be the tighter one too
+        let point = fit_ratio(Point::new(100, 10), Point::new(300, -50), ratio, 1000, 1000);
+        assert_eq!(point, Point::new(120, 0));
+    }
+
+    #[test]
+    fn nested_templates_create_their_directories() {
+        let root = std::env::temp_dir().join(format!("sleek-test-{}", std::process::id()));
+        let args = Args {
+            template: Some("dir/%Y/%m/shot".to_owned()),
+            output_dir: Some(root.to_string_lossy().into_owned()),
+            ..Args::default()
+        };
+
+        let path = std::path::PathBuf::from(output_path(&args).unwrap());
+        let now = Local::now();
+        let expected = root
+            .join("dir")
+            .join(now.format("%Y").to_string())
+            .join(now.format("%m").to_string());
+        assert_eq!(path, expected.join("shot.png"));
+        assert!(expected.is_dir());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn bad_templates_are_an_error() {
+        let args = Args {
+            template: Some("sleek %Q".to_owned()),
+            ..Args::default()
+        };
+        assert!(matches!(output_path(&args), Err(SleekError::Template(..))));
+    }
+
+    #[test]
+    fn icon_sets_have_the_requested_sizes() {
+        // not square on purpose, every icon is stretched to NxN regardless
+        let image = image::RgbImage::new(40, 30);
+        let icons: Vec<_> = icon_set("shot.jpg", &image, &[16, 32, 1]).collect();
+
+        let names: Vec<&str> = icons.iter().map(|(path, _)| path.as_str()).collect();
+        assert_eq!(names, ["shot-16x16.jpg", "shot-32x32.jpg", "shot-1x1.jpg"]);
+        for ((_, icon), size) in icons.iter().zip([16, 32, 1]) {
+            assert_eq!(icon.dimensions(), (size, size));
+        }
+    }
+
+    #[test]
+    fn decodes_what_it_encodes() {
+        let visuals = [
+            ScreenData::new(0, 0, 24, 0xff0000, 0x00ff00, 0x0000ff),
+            ScreenData::new(0, 0, 16, 0xf800, 0x07e0, 0x001f),
+            ScreenData::new(0, 0, 30, 0x3ff00000, 0x000ffc00, 0x000003ff),
+        ];
+        for screen in visuals {
+            for colour in [
+                RGB::new(0, 0, 0),
+                RGB::new(255, 255, 255),
+                RGB::new(255, 0, 255),
+            ] {
+                let decoded = screen.decode(screen.encode(colour) as u32);
+                assert_eq!(
+                    (decoded.r, decoded.g, decoded.b),
+                    (colour.r, colour.g, colour.b)
+                );
+            }
+        }
+    }
+}
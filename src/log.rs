@@ -0,0 +1,49 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+
+// --quiet and --verbose, whichever was given last
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Level {
+    Quiet,
+    Normal,
+    Verbose,
+}
+
+static LEVEL: AtomicU8 = AtomicU8::new(Level::Normal as u8);
+
+pub fn set_level(level: Level) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+fn level() -> Level {
+    match LEVEL.load(Ordering::Relaxed) {
+        0 => Level::Quiet,
+        1 => Level::Normal,
+        _ => Level::Verbose,
+    }
+}
+
+// the saved path on stdout, the one thing sleek says on success
+pub fn saved(path: &std::path::Path) {
+    if level() > Level::Quiet {
+        println!("{}", path.display());
+    }
+}
+
+// each step of a capture, for --verbose
+pub fn info(message: &str) {
+    if level() >= Level::Verbose {
+        eprintln!("sleek: {message}");
+    }
+}
+
+// the capture carries on regardless, so these still show with --quiet
+pub fn warn(message: &str) {
+    eprintln!("warning: {message}");
+}
+
+// SLEEK_DEBUG=1 turns on diagnostics that are too noisy for normal use
+pub fn debug(message: &str) {
+    if std::env::var_os("SLEEK_DEBUG").is_some() {
+        eprintln!("debug: {message}");
+    }
+}
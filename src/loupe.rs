@@ -0,0 +1,102 @@
+use crate::args::Args;
+use crate::{x11_safe, Point, Rect, RenderWindow};
+
+const MARGIN: i32 = 16;
+const BORDER: i32 = 2;
+
+// a zoomed copy of the pixels around the pointer, kept in the top right corner
+// and moved to the bottom right whenever the pointer gets close to it. it's
+// read from and drawn over the overlay only, so it never ends up in a capture
+pub fn draw(render_window: &mut RenderWindow, args: &Args) {
+    let (screen_width, screen_height) = (
+        render_window.screen_data.width,
+        render_window.screen_data.height,
+    );
+    let size = args.loupe_size.min(screen_width).min(screen_height);
+    let zoom = render_window.scaled(args.loupe_zoom);
+    let side = size * zoom;
+    let pointer = render_window.pointer;
+
+    let mut frame = Rect::new(
+        screen_width - side - MARGIN - BORDER,
+        MARGIN,
+        side + 2 * BORDER,
+        side + 2 * BORDER,
+    );
+    if pointer.x >= frame.x - MARGIN && pointer.y < frame.y + frame.height + MARGIN {
+        frame.y = screen_height - frame.height - MARGIN;
+    }
+
+    // the block stays whole at the screen's edges, the marker moves instead
+    let source = Point::new(
+        (pointer.x - size / 2).clamp(0, screen_width - size),
+        (pointer.y - size / 2).clamp(0, screen_height - size),
+    );
+    let Ok(image) = x11_safe::get_image(
+        render_window.display,
+        render_window.pixmap,
+        source.x,
+        source.y,
+        size as u32,
+        size as u32,
+    ) else {
+        return;
+    };
+
+    let (display, buffer, gc) = (
+        render_window.display,
+        render_window.buffer,
+        render_window.graphics_context,
+    );
+    let line_colour = render_window.screen_data.encode(render_window.line_colour);
+
+    x11_safe::set_foreground(display, gc, line_colour);
+    fill(render_window, frame);
+
+    let inside = Point::new(frame.x + BORDER, frame.y + BORDER);
+    for row in 0..size {
+        for column in 0..size {
+            x11_safe::set_foreground(display, gc, image.pixel(column, row));
+            x11_safe::fill_rectangle(
+                display,
+                buffer,
+                gc,
+                inside.x + column * zoom,
+                inside.y + row * zoom,
+                zoom as u32,
+                zoom as u32,
+            );
+        }
+    }
+
+    // a box around the pixel under the pointer
+    x11_safe::set_foreground(display, gc, line_colour);
+    let marker = Rect::new(
+        inside.x + (pointer.x - source.x) * zoom - 1,
+        inside.y + (pointer.y - source.y) * zoom - 1,
+        zoom + 2,
+        zoom + 2,
+    );
+    for edge in [
+        Rect::new(marker.x, marker.y, marker.width, 1),
+        Rect::new(marker.x, marker.y + marker.height - 1, marker.width, 1),
+        Rect::new(marker.x, marker.y, 1, marker.height),
+        Rect::new(marker.x + marker.width - 1, marker.y, 1, marker.height),
+    ] {
+        fill(render_window, edge);
+    }
+
+    render_window.mark_drawn(frame);
+}
+
+fn fill(render_window: &RenderWindow, rect: Rect) {
+    x11_safe::fill_rectangle(
+        render_window.display,
+        render_window.buffer,
+        render_window.graphics_context,
+        rect.x,
+        rect.y,
+        rect.width as u32,
+        rect.height as u32,
+    );
+}
@@ -0,0 +1,38 @@
+use crate::{x11_safe, Point};
+
+#[derive(Copy, Clone, Debug)]
+pub struct Monitor {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+    pub rotation: u16,
+}
+
+impl Monitor {
+    pub fn contains(&self, point: Point) -> bool {
+        point.x >= self.x
+            && point.y >= self.y
+            && point.x < self.x + self.width
+            && point.y < self.y + self.height
+    }
+}
+
+pub fn monitors(display: x11_safe::Display, root: u64) -> Vec<Monitor> {
+    x11_safe::crtcs(display, root)
+        .into_iter()
+        .map(|crtc| Monitor {
+            x: crtc.x,
+            y: crtc.y,
+            width: crtc.width as i32,
+            height: crtc.height as i32,
+            rotation: crtc.rotation,
+        })
+        .collect()
+}
+
+pub fn monitor_at(display: x11_safe::Display, root: u64, point: Point) -> Option<Monitor> {
+    monitors(display, root)
+        .into_iter()
+        .find(|monitor| monitor.contains(point))
+}
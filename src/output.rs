@@ -0,0 +1,195 @@
+use crate::jpeg::{self, Subsampling};
+use crate::rotation;
+use image::codecs::bmp::BmpEncoder;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::pnm::{PnmEncoder, PnmSubtype, SampleEncoding};
+use image::codecs::webp::WebPEncoder;
+use image::{DynamicImage, RgbImage, RgbaImage};
+use std::io::Write;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Format {
+    Png,
+    Jpeg,
+    WebP,
+    Bmp,
+    Ppm,
+}
+
+impl Format {
+    // None for extensions sleek can't write, those get .png appended instead
+    pub fn from_path(path: &str) -> Option<Self> {
+        let extension = std::path::Path::new(path)
+            .extension()?
+            .to_str()?
+            .to_ascii_lowercase();
+        match extension.as_str() {
+            "png" => Some(Self::Png),
+            "jpg" | "jpeg" => Some(Self::Jpeg),
+            "webp" => Some(Self::WebP),
+            "bmp" => Some(Self::Bmp),
+            "ppm" => Some(Self::Ppm),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg => "jpeg",
+            Self::WebP => "webp",
+            Self::Bmp => "bmp",
+            Self::Ppm => "ppm",
+        }
+    }
+}
+
+// everything that goes into a PNG besides the pixels, the other formats
+// ignore it
+#[derive(Default)]
+pub struct PngChunks {
+    pub orientation: Option<u16>,
+    // tEXt keyword and value pairs
+    pub text: Vec<(String, String)>,
+}
+
+// single line in front of the encoded image so pipe consumers don't have to sniff the format
+pub fn write_stream_header<W: Write + ?Sized>(
+    writer: &mut W,
+    format: &str,
+    width: u32,
+    height: u32,
+) -> std::io::Result<()> {
+    writeln!(writer, "SLEEK1 {format} {width} {height}")
+}
+
+pub fn write_png<W: Write>(
+    writer: W,
+    image: &RgbImage,
+    chunks: &PngChunks,
+) -> Result<(), png::EncodingError> {
+    encode_png(
+        writer,
+        image.width(),
+        image.height(),
+        png::ColorType::Rgb,
+        image.as_raw(),
+        chunks,
+    )
+}
+
+pub fn write_rgba_png<W: Write>(
+    writer: W,
+    image: &RgbaImage,
+    chunks: &PngChunks,
+) -> Result<(), png::EncodingError> {
+    encode_png(
+        writer,
+        image.width(),
+        image.height(),
+        png::ColorType::Rgba,
+        image.as_raw(),
+        chunks,
+    )
+}
+
+fn encode_png<W: Write>(
+    writer: W,
+    width: u32,
+    height: u32,
+    colour_type: png::ColorType,
+    data: &[u8],
+    chunks: &PngChunks,
+) -> Result<(), png::EncodingError> {
+    let mut encoder = png::Encoder::new(writer, width, height);
+    encoder.set_color(colour_type);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    for (keyword, text) in &chunks.text {
+        encoder.add_text_chunk(keyword.clone(), text.clone())?;
+    }
+
+    let mut writer = encoder.write_header()?;
+    if let Some(orientation) = chunks.orientation {
+        writer.write_chunk(
+            png::chunk::ChunkType(*b"eXIf"),
+            &rotation::exif_payload(orientation),
+        )?;
+    }
+    writer.write_image_data(data)?;
+    writer.finish()
+}
+
+// quality, subsampling and progressive scans only mean anything to JPEG
+#[derive(Copy, Clone)]
+pub struct JpegOptions {
+    pub quality: u8,
+    pub subsampling: Subsampling,
+    pub progressive: bool,
+}
+
+// the image crate's WebP encoder is lossless only and PNG always is
+pub fn write_image<W: Write>(
+    writer: W,
+    format: Format,
+    image: &DynamicImage,
+    chunks: &PngChunks,
+    jpeg: JpegOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match (format, image) {
+        (Format::Png, DynamicImage::ImageRgba8(image)) => write_rgba_png(writer, image, chunks)?,
+        (Format::Png, image) => write_png(writer, &image.to_rgb8(), chunks)?,
+        // JPEG has no alpha channel. the image crate's encoder does baseline
+        // 4:4:4, anything else needs sleek's own
+        (Format::Jpeg, image) if jpeg.subsampling == Subsampling::S444 && !jpeg.progressive => {
+            JpegEncoder::new_with_quality(writer, jpeg.quality).encode_image(&image.to_rgb8())?
+        }
+        (Format::Jpeg, image) => jpeg::encode(
+            writer,
+            &image.to_rgb8(),
+            jpeg.quality,
+            jpeg.subsampling,
+            jpeg.progressive,
+        )?,
+        (Format::WebP, DynamicImage::ImageRgba8(image)) => WebPEncoder::new_lossless(writer)
+            .encode(
+                image.as_raw(),
+                image.width(),
+                image.height(),
+                image::ColorType::Rgba8,
+            )?,
+        (Format::WebP, image) => {
+            let image = image.to_rgb8();
+            WebPEncoder::new_lossless(writer).encode(
+                image.as_raw(),
+                image.width(),
+                image.height(),
+                image::ColorType::Rgb8,
+            )?
+        }
+        // both are written without alpha, few readers of either would honour it
+        (Format::Bmp, image) => {
+            let image = image.to_rgb8();
+            let mut writer = writer;
+            BmpEncoder::new(&mut writer).encode(
+                image.as_raw(),
+                image.width(),
+                image.height(),
+                image::ColorType::Rgb8,
+            )?
+        }
+        // binary P6, what ImageMagick and netpbm expect on a pipe
+        (Format::Ppm, image) => {
+            let image = image.to_rgb8();
+            PnmEncoder::new(writer)
+                .with_subtype(PnmSubtype::Pixmap(SampleEncoding::Binary))
+                .encode(
+                    image.as_raw().as_slice(),
+                    image.width(),
+                    image.height(),
+                    image::ColorType::Rgb8,
+                )?
+        }
+    }
+    Ok(())
+}
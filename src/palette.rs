@@ -0,0 +1,126 @@
+use crate::args::Args;
+use crate::error::SleekError;
+use crate::x11_safe::{self, Event};
+use crate::{hud, output, Point, Rect, RenderWindow, RGB};
+use x11::xlib::Button1;
+
+const SWATCH_SIZE: i32 = 24;
+const SWATCH_GAP: i32 = 4;
+
+// reads from the frozen background so the swatches drawn on the window are never picked
+fn pixel_at(render_window: &RenderWindow, x: i32, y: i32) -> Result<RGB, SleekError> {
+    let image = x11_safe::get_image(render_window.display, render_window.pixmap, x, y, 1, 1)?;
+    let pixel = image.pixel(0, 0);
+
+    Ok(render_window.screen_data.decode(pixel as u32))
+}
+
+fn draw_palette(render_window: &mut RenderWindow, palette: &[RGB]) {
+    let y = render_window.screen_data.height - SWATCH_SIZE - SWATCH_GAP;
+
+    for (i, colour) in palette.iter().enumerate() {
+        let x = SWATCH_GAP + i as i32 * (SWATCH_SIZE + SWATCH_GAP);
+
+        x11_safe::set_foreground(
+            render_window.display,
+            render_window.graphics_context,
+            render_window.screen_data.encode(*colour),
+        );
+        x11_safe::fill_rectangle(
+            render_window.display,
+            render_window.buffer,
+            render_window.graphics_context,
+            x,
+            y,
+            SWATCH_SIZE as u32,
+            SWATCH_SIZE as u32,
+        );
+        render_window.mark_drawn(Rect::new(x, y, SWATCH_SIZE, SWATCH_SIZE));
+    }
+    render_window.present();
+
+    x11_safe::set_foreground(
+        render_window.display,
+        render_window.graphics_context,
+        render_window.screen_data.encode(render_window.line_colour),
+    );
+}
+
+// one square per colour, left to right in pick order
+fn swatch_image(palette: &[RGB]) -> image::RgbImage {
+    let size = SWATCH_SIZE as u32;
+    image::RgbImage::from_fn(size * palette.len() as u32, size, |x, _| {
+        let colour = palette[(x / size) as usize];
+        image::Rgb([colour.r, colour.g, colour.b])
+    })
+}
+
+fn output_palette(args: &Args, palette: &[RGB]) -> Result<(), SleekError> {
+    if palette.is_empty() {
+        return Ok(());
+    }
+
+    match &args.swatch_out {
+        Some(path) => {
+            let file = std::fs::File::create(path).map_err(|e| SleekError::write(path, e))?;
+            output::write_png(
+                std::io::BufWriter::new(file),
+                &swatch_image(palette),
+                &Default::default(),
+            )
+            .map_err(|e| SleekError::write(path, e))?;
+        }
+        None => {
+            for colour in palette {
+                println!("{colour}");
+            }
+        }
+    }
+    Ok(())
+}
+
+// a one shot eyedropper, the colour of the first click is printed
+pub fn pick_colour(render_window: &mut RenderWindow, args: &Args) -> Result<(), SleekError> {
+    loop {
+        match x11_safe::next_event(render_window.display) {
+            Event::Motion { x, y } => {
+                let colour = pixel_at(render_window, x, y)?;
+                render_window.reset();
+                hud::draw_colour(render_window, colour, Point::new(x, y));
+                render_window.present();
+            }
+            Event::ButtonPress { button, x, y, .. } if button == Button1 => {
+                let colour = pixel_at(render_window, x, y)?;
+                if args.pick_rgb {
+                    println!("{colour} rgb({}, {}, {})", colour.r, colour.g, colour.b);
+                } else {
+                    println!("{colour}");
+                }
+                return Ok(());
+            }
+            Event::KeyPress { keysym, .. } if args.is_cancel(keysym) => return Ok(()),
+            _ => {}
+        }
+    }
+}
+
+pub fn pick_palette(render_window: &mut RenderWindow, args: &Args) -> Result<(), SleekError> {
+    let mut palette: Vec<RGB> = Vec::new();
+
+    loop {
+        match x11_safe::next_event(render_window.display) {
+            Event::ButtonPress { button, x, y, .. } if button == Button1 => {
+                palette.push(pixel_at(render_window, x, y)?);
+                draw_palette(render_window, &palette);
+            }
+            Event::KeyPress { keysym, .. } => {
+                if args.is_cancel(keysym) {
+                    return Ok(());
+                } else if args.is_confirm(keysym) {
+                    return output_palette(args, &palette);
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use image::DynamicImage;
+use x11::xrandr::{RR_Rotate_180, RR_Rotate_270, RR_Rotate_90};
+
+// bake: pixels are saved the way the monitor shows them (what XGetImage returns)
+// tag: pixels are saved in the panel's native layout with an EXIF orientation
+//      tag so viewers still display them upright
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RotationMode {
+    Bake,
+    Tag,
+}
+
+impl std::str::FromStr for RotationMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bake" => Ok(Self::Bake),
+            "tag" => Ok(Self::Tag),
+            _ => Err("expected 'bake' or 'tag'".to_owned()),
+        }
+    }
+}
+
+// XRandR rotations are counter-clockwise, EXIF orientations describe the
+// transform a viewer has to apply to display the stored pixels
+pub fn exif_orientation(rotation: u16) -> u16 {
+    match rotation as i32 & 0xf {
+        r if r == RR_Rotate_90 => 8,
+        r if r == RR_Rotate_180 => 3,
+        r if r == RR_Rotate_270 => 6,
+        _ => 1,
+    }
+}
+
+pub fn unrotate(image: DynamicImage, rotation: u16) -> DynamicImage {
+    match rotation as i32 & 0xf {
+        r if r == RR_Rotate_90 => image.rotate90(),
+        r if r == RR_Rotate_180 => image.rotate180(),
+        r if r == RR_Rotate_270 => image.rotate270(),
+        _ => image,
+    }
+}
+
+// big endian TIFF header followed by a single IFD holding just the orientation
+pub fn exif_payload(orientation: u16) -> Vec<u8> {
+    let mut exif = Vec::with_capacity(26);
+    exif.extend_from_slice(b"MM\x00\x2a");
+    exif.extend_from_slice(&8u32.to_be_bytes());
+    exif.extend_from_slice(&1u16.to_be_bytes());
+    exif.extend_from_slice(&0x0112u16.to_be_bytes());
+    exif.extend_from_slice(&3u16.to_be_bytes());
+    exif.extend_from_slice(&1u32.to_be_bytes());
+    exif.extend_from_slice(&orientation.to_be_bytes());
+    exif.extend_from_slice(&[0, 0]);
+    exif.extend_from_slice(&0u32.to_be_bytes());
+    exif
+}
@@ -0,0 +1,127 @@
+use image::imageops::FilterType;
+use image::{ImageBuffer, Pixel};
+
+// --scale and --resize, whichever was given last
+#[derive(Copy, Clone, Debug)]
+pub enum Scale {
+    Factor(f64),
+    // a missing side follows the other one so the aspect ratio is kept
+    Size(Option<u32>, Option<u32>),
+}
+
+impl Scale {
+    pub fn factor(s: &str) -> Result<Self, String> {
+        let factor: f64 = s.parse().map_err(|e| format!("{e}"))?;
+        if !factor.is_finite() || factor <= 0.0 {
+            return Err("must be greater than 0".to_owned());
+        }
+        Ok(Self::Factor(factor))
+    }
+
+    // WxH, Wx or xH
+    pub fn size(s: &str) -> Result<Self, String> {
+        let (width, height) = s.split_once('x').ok_or("expected WxH, Wx or xH")?;
+        let side = |side: &str| -> Result<Option<u32>, String> {
+            match side.trim() {
+                "" => Ok(None),
+                side => match side.parse() {
+                    Ok(0) => Err("sides must be at least 1".to_owned()),
+                    Ok(side) => Ok(Some(side)),
+                    Err(e) => Err(format!("{e}")),
+                },
+            }
+        };
+        match (side(width)?, side(height)?) {
+            (None, None) => Err("expected WxH, Wx or xH".to_owned()),
+            (width, height) => Ok(Self::Size(width, height)),
+        }
+    }
+
+    fn dimensions(self, width: u32, height: u32) -> (u32, u32) {
+        let scaled = |side: u32, factor: f64| ((side as f64 * factor).round() as u32).max(1);
+        match self {
+            Self::Factor(factor) => (scaled(width, factor), scaled(height, factor)),
+            Self::Size(Some(w), Some(h)) => (w, h),
+            Self::Size(Some(w), None) => (w, scaled(height, w as f64 / width as f64)),
+            Self::Size(None, Some(h)) => (scaled(width, h as f64 / height as f64), h),
+            Self::Size(None, None) => (width, height),
+        }
+    }
+}
+
+pub fn filter(s: &str) -> Result<FilterType, String> {
+    match s.to_ascii_lowercase().as_str() {
+        "nearest" => Ok(FilterType::Nearest),
+        "triangle" | "bilinear" => Ok(FilterType::Triangle),
+        "catmullrom" | "bicubic" => Ok(FilterType::CatmullRom),
+        "gaussian" => Ok(FilterType::Gaussian),
+        "lanczos3" | "lanczos" => Ok(FilterType::Lanczos3),
+        _ => Err("expected nearest, triangle, catmullrom, gaussian or lanczos3".to_owned()),
+    }
+}
+
+pub fn apply<P: Pixel<Subpixel = u8> + 'static>(
+    image: ImageBuffer<P, Vec<u8>>,
+    scale: Scale,
+    filter: FilterType,
+) -> ImageBuffer<P, Vec<u8>> {
+    let (width, height) = scale.dimensions(image.width(), image.height());
+    if (width, height) == image.dimensions() {
+        return image;
+    }
+    image::imageops::resize(&image, width, height, filter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_sizes() {
+        assert!(matches!(
+            Scale::size("800x600"),
+            Ok(Scale::Size(Some(800), Some(600)))
+        ));
+        assert!(matches!(
+            Scale::size("800x"),
+            Ok(Scale::Size(Some(800), None))
+        ));
+        assert!(matches!(
+            Scale::size("x600"),
+            Ok(Scale::Size(None, Some(600)))
+        ));
+    }
+
+    #[test]
+    fn rejects_zero_and_missing_sides() {
+        assert!(Scale::size("0x600").is_err());
+        assert!(Scale::size("800x0").is_err());
+        assert!(Scale::size("x").is_err());
+        assert!(Scale::size("800").is_err());
+        assert!(Scale::size("-1x600").is_err());
+        assert!(Scale::factor("0").is_err());
+        assert!(Scale::factor("inf").is_err());
+    }
+
+    #[test]
+    fn a_missing_side_keeps_the_aspect_ratio() {
+        assert_eq!(Scale::Size(Some(100), None).dimensions(300, 200), (100, 67));
+        assert_eq!(
+            Scale::Size(None, Some(100)).dimensions(300, 200),
+            (150, 100)
+        );
+        // rounds to the nearest, never down to nothing
+        assert_eq!(Scale::Size(None, Some(1)).dimensions(3, 2), (2, 1));
+        assert_eq!(Scale::Size(Some(1), None).dimensions(1000, 1), (1, 1));
+    }
+
+    #[test]
+    fn factors_and_exact_sizes() {
+        assert_eq!(Scale::Factor(0.5).dimensions(301, 200), (151, 100));
+        assert_eq!(Scale::Factor(0.001).dimensions(300, 200), (1, 1));
+        assert_eq!(
+            Scale::Size(Some(10), Some(20)).dimensions(300, 200),
+            (10, 20)
+        );
+    }
+}
@@ -0,0 +1,48 @@
+use std::ffi::CString;
+use std::io;
+
+// the segment holds nothing but the pixels: height rows of width * 4 bytes,
+// RGBA with 8 bits per channel and no padding. sleek only creates it, the
+// consumer is expected to shm_unlink once it has read the pixels
+pub fn write_rgba(name: &str, width: u32, height: u32, pixels: &[u8]) -> io::Result<String> {
+    let name = if name.starts_with('/') {
+        name.to_owned()
+    } else {
+        format!("/{name}")
+    };
+    let c_name = CString::new(name.as_str())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "name contains a nul byte"))?;
+    let len = (width * height * 4) as usize;
+    debug_assert_eq!(pixels.len(), len);
+
+    unsafe {
+        let fd = libc::shm_open(c_name.as_ptr(), libc::O_CREAT | libc::O_RDWR, 0o600);
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        if libc::ftruncate(fd, len as libc::off_t) != 0 {
+            let error = io::Error::last_os_error();
+            libc::close(fd);
+            return Err(error);
+        }
+
+        let memory = libc::mmap(
+            std::ptr::null_mut(),
+            len,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_SHARED,
+            fd,
+            0,
+        );
+        libc::close(fd);
+        if memory == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+
+        std::ptr::copy_nonoverlapping(pixels.as_ptr(), memory as *mut u8, len);
+        libc::munmap(memory, len);
+    }
+
+    Ok(name)
+}
@@ -0,0 +1,128 @@
+use crate::monitor::Monitor;
+use crate::Rect;
+use chrono::prelude::*;
+
+pub struct WindowInfo {
+    pub title: Option<String>,
+    pub class: Option<String>,
+}
+
+pub struct Metadata {
+    pub geometry: Rect,
+    pub monitor: Option<Monitor>,
+    pub window: Option<WindowInfo>,
+}
+
+// shot.png -> shot.json
+pub fn sidecar_path(filepath: &str) -> String {
+    std::path::Path::new(filepath)
+        .with_extension("json")
+        .to_string_lossy()
+        .into_owned()
+}
+
+// written atomically like the image, so anything watching the directory never
+// sees a half written sidecar next to a finished image
+pub fn write(filepath: &str, metadata: &Metadata) -> Result<(), Box<dyn std::error::Error>> {
+    let json = to_json(filepath, metadata);
+    crate::write_atomically(&sidecar_path(filepath), |file| {
+        file.write_all(json.as_bytes())?;
+        Ok(())
+    })
+}
+
+fn to_json(filepath: &str, metadata: &Metadata) -> String {
+    let geometry = metadata.geometry;
+
+    let monitor = match metadata.monitor {
+        Some(monitor) => format!(
+            "{{\"x\": {}, \"y\": {}, \"width\": {}, \"height\": {}}}",
+            monitor.x, monitor.y, monitor.width, monitor.height
+        ),
+        None => "null".to_owned(),
+    };
+
+    let window = match &metadata.window {
+        Some(window) => format!(
+            "{{\"title\": {}, \"class\": {}}}",
+            optional_string(&window.title),
+            optional_string(&window.class)
+        ),
+        None => "null".to_owned(),
+    };
+
+    format!(
+        "{{\n  \"timestamp\": {},\n  \"image\": {},\n  \"geometry\": {{\"x\": {}, \"y\": {}, \"width\": {}, \"height\": {}}},\n  \"monitor\": {monitor},\n  \"window\": {window},\n  \"version\": {}\n}}\n",
+        string(&Local::now().to_rfc3339()),
+        string(filepath),
+        geometry.x,
+        geometry.y,
+        geometry.width,
+        geometry.height,
+        string(env!("CARGO_PKG_VERSION")),
+    )
+}
+
+// the same details as tEXt chunks, for --metadata. Creation Time and Software
+// are keywords from the PNG spec, the rest are sleek's own
+pub fn png_text(metadata: &Metadata) -> Vec<(String, String)> {
+    let geometry = metadata.geometry;
+    let mut text = vec![
+        ("Creation Time".to_owned(), Local::now().to_rfc3339()),
+        (
+            "Software".to_owned(),
+            format!("sleek {}", env!("CARGO_PKG_VERSION")),
+        ),
+        (
+            "Geometry".to_owned(),
+            format!(
+                "{},{} {}x{}",
+                geometry.x, geometry.y, geometry.width, geometry.height
+            ),
+        ),
+    ];
+    if let Some(monitor) = metadata.monitor {
+        text.push((
+            "Monitor".to_owned(),
+            format!(
+                "{}x{}+{}+{}",
+                monitor.width, monitor.height, monitor.x, monitor.y
+            ),
+        ));
+    }
+    if let Some(hostname) = hostname() {
+        text.push(("Hostname".to_owned(), hostname));
+    }
+    text
+}
+
+fn hostname() -> Option<String> {
+    let mut buffer = [0u8; 256];
+    if unsafe { libc::gethostname(buffer.as_mut_ptr() as *mut libc::c_char, buffer.len()) } != 0 {
+        return None;
+    }
+    let end = buffer.iter().position(|&b| b == 0)?;
+    Some(String::from_utf8_lossy(&buffer[..end]).into_owned())
+}
+
+fn optional_string(value: &Option<String>) -> String {
+    value.as_deref().map_or("null".to_owned(), string)
+}
+
+fn string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len() + 2);
+    escaped.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
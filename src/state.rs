@@ -0,0 +1,41 @@
+use crate::Rect;
+use std::path::PathBuf;
+
+// the last saved selection in root coordinates, as x,y,w,h, for --last. lives
+// in $XDG_STATE_HOME/sleek since losing it costs nothing
+fn path() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_STATE_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME")?)
+            .join(".local")
+            .join("state"),
+    };
+    Some(base.join("sleek").join("last-selection"))
+}
+
+pub fn save_last(rect: Rect) -> std::io::Result<()> {
+    let path = path().ok_or_else(|| std::io::Error::other("HOME isn't set"))?;
+    if let Some(directory) = path.parent() {
+        std::fs::create_dir_all(directory)?;
+    }
+    std::fs::write(
+        path,
+        format!("{},{},{},{}\n", rect.x, rect.y, rect.width, rect.height),
+    )
+}
+
+// None when nothing has been saved yet
+pub fn load_last() -> Result<Option<Rect>, String> {
+    let Some(path) = path() else {
+        return Ok(None);
+    };
+    match std::fs::read_to_string(&path) {
+        Ok(contents) => contents
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|e| format!("{}: {e}", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("{}: {e}", path.display())),
+    }
+}
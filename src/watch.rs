@@ -0,0 +1,65 @@
+use crate::args::Args;
+use crate::error::SleekError;
+use crate::{grab, output_path, suffixed_path, write_capture, x11_safe, Point, Rect, RenderWindow};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+// how long the region has to stay unchanged after a change before it's saved
+const SETTLE_TIME: Duration = Duration::from_millis(500);
+
+static STOP: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn stop(_: libc::c_int) {
+    STOP.store(true, Ordering::SeqCst);
+}
+
+pub fn watch_region(
+    render_window: &mut RenderWindow,
+    args: &Args,
+    point_one: Point,
+    point_two: Point,
+) -> Result<(), SleekError> {
+    let min = point_one.min(&point_two);
+    let max = point_one.max(&point_two);
+
+    unsafe {
+        libc::signal(libc::SIGINT, stop as *const () as libc::sighandler_t);
+    }
+
+    x11_safe::unmap_window(render_window.display, render_window.window);
+    x11_safe::sync(render_window.display);
+
+    let root = render_window.root;
+    let region = Rect::new(min.x, min.y, max.x - min.x, max.y - min.y);
+
+    // the overlay is gone so this polls the root, which --monitor puts elsewhere
+    let origin = render_window.origin;
+    let (min, max) = (
+        Point::new(min.x + origin.x, min.y + origin.y),
+        Point::new(max.x + origin.x, max.y + origin.y),
+    );
+    let start = Instant::now();
+    let max_duration = Duration::from_secs(args.max_duration);
+
+    let mut last = grab(render_window, root, min, max)?;
+    let mut changed_at = Some(start);
+    let mut saved = 0;
+
+    while !STOP.load(Ordering::SeqCst) && saved < args.max_count && start.elapsed() < max_duration {
+        std::thread::sleep(POLL_INTERVAL);
+
+        let current = grab(render_window, root, min, max)?;
+        if current != last {
+            last = current;
+            changed_at = Some(Instant::now());
+        } else if changed_at.is_some_and(|t| t.elapsed() >= SETTLE_TIME) {
+            saved += 1;
+            // several states can settle within the same second so the counter keeps names unique
+            let filepath = suffixed_path(&output_path(args)?, &saved.to_string());
+            write_capture(render_window, args, &filepath, last.clone().into(), region)?;
+            changed_at = None;
+        }
+    }
+    Ok(())
+}
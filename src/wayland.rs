@@ -0,0 +1,694 @@
+use crate::monitor::Monitor;
+use crate::window::Capture;
+use crate::{Point, Rect};
+use std::io::{Read, Write};
+use std::os::unix::fs::FileExt;
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+use std::os::unix::net::UnixStream;
+
+// just enough of the Wayland wire protocol to drive wlr-screencopy, so sleek
+// can capture natively on wlroots compositors without any new dependencies.
+// there's no way to show an overlay from here, so only captures of a known
+// region (--geometry, --fullscreen) go this way, see backend.rs
+
+// object ids are picked by the client, 1 is always the wl_display
+const DISPLAY: u32 = 1;
+
+// wl_shm formats, ARGB and XRGB have their own codes and the rest are fourccs
+const ARGB8888: u32 = 0;
+const XRGB8888: u32 = 1;
+const ABGR8888: u32 = 0x3432_4241;
+const XBGR8888: u32 = 0x3432_4258;
+
+// WAYLAND_DISPLAY is what every client checks, there's no other reliable sign
+pub fn session() -> bool {
+    std::env::var_os("WAYLAND_DISPLAY").is_some_and(|display| !display.is_empty())
+}
+
+// rect in the compositor's layout, None for everything. outputs with a scale
+// are captured at their full resolution and scaled down to the layout's size
+pub fn capture(rect: Option<Rect>, cursor: bool) -> Result<Capture, String> {
+    let mut connection = Connection::open()?;
+    let globals = Globals::bind(&mut connection)?;
+
+    let outputs: Vec<Monitor> = globals.outputs.iter().map(Output::logical).collect();
+    let desktop = outputs
+        .iter()
+        .map(|output| Rect::new(output.x, output.y, output.width, output.height))
+        .reduce(|a, b| {
+            let (x, y) = (a.x.min(b.x), a.y.min(b.y));
+            Rect::new(
+                x,
+                y,
+                (a.x + a.width).max(b.x + b.width) - x,
+                (a.y + a.height).max(b.y + b.height) - y,
+            )
+        })
+        .ok_or("the compositor has no outputs")?;
+    let target = rect
+        .unwrap_or(desktop)
+        .intersect(&desktop)
+        .ok_or("the region is outside every output")?;
+
+    let mut canvas = image::RgbImage::new(target.width as u32, target.height as u32);
+    for (output, logical) in globals.outputs.iter().zip(&outputs) {
+        let bounds = Rect::new(logical.x, logical.y, logical.width, logical.height);
+        let Some(part) = target.intersect(&bounds) else {
+            continue;
+        };
+
+        let region = Rect::new(
+            part.x - logical.x,
+            part.y - logical.y,
+            part.width,
+            part.height,
+        );
+        let image = connection.capture_region(&globals, output.id, region, cursor)?;
+        // turned the way the layout shows it first, only then is a size
+        // mismatch down to the output's scale
+        let mut image = output.untransform(image);
+        if image.dimensions() != (part.width as u32, part.height as u32) {
+            image = image::imageops::resize(
+                &image,
+                part.width as u32,
+                part.height as u32,
+                image::imageops::FilterType::Triangle,
+            );
+        }
+        image::imageops::replace(
+            &mut canvas,
+            &image,
+            (part.x - target.x) as i64,
+            (part.y - target.y) as i64,
+        );
+    }
+
+    let centre = Point::new(target.x + target.width / 2, target.y + target.height / 2);
+    Ok(Capture {
+        image: image::DynamicImage::ImageRgb8(canvas),
+        geometry: target,
+        monitor: outputs.into_iter().find(|output| output.contains(centre)),
+        info: None,
+    })
+}
+
+enum Arg<'a> {
+    Uint(u32),
+    Int(i32),
+    Str(&'a str),
+}
+
+struct Message {
+    object: u32,
+    opcode: u16,
+    body: Vec<u8>,
+}
+
+impl Message {
+    fn args(&self) -> Args<'_> {
+        Args {
+            body: &self.body,
+            at: 0,
+        }
+    }
+}
+
+// reads a message's arguments in order, anything short reads as zero
+struct Args<'a> {
+    body: &'a [u8],
+    at: usize,
+}
+
+impl Args<'_> {
+    fn uint(&mut self) -> u32 {
+        let word = self
+            .body
+            .get(self.at..self.at + 4)
+            .map_or(0, |bytes| u32::from_ne_bytes(bytes.try_into().unwrap()));
+        self.at += 4;
+        word
+    }
+
+    fn int(&mut self) -> i32 {
+        self.uint() as i32
+    }
+
+    fn string(&mut self) -> String {
+        let length = self.uint() as usize;
+        let bytes = self.body.get(self.at..self.at + length).unwrap_or_default();
+        self.at += length.next_multiple_of(4);
+        // the length counts the trailing NUL
+        String::from_utf8_lossy(bytes.strip_suffix(&[0]).unwrap_or(bytes)).into_owned()
+    }
+}
+
+#[derive(Default)]
+struct Output {
+    id: u32,
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+    scale: i32,
+    transform: i32,
+    // from xdg-output, the only way to get it right with fractional scaling
+    logical_position: Option<(i32, i32)>,
+    logical_size: Option<(i32, i32)>,
+}
+
+impl Output {
+    // the area it covers in the layout. without xdg-output it's worked out
+    // from the mode, which is in the output's own pixels, and the integer scale
+    fn logical(&self) -> Monitor {
+        let (x, y) = self.logical_position.unwrap_or((self.x, self.y));
+        let (width, height) = self.logical_size.unwrap_or_else(|| {
+            let (width, height) = if self.transform % 2 == 1 {
+                (self.height, self.width)
+            } else {
+                (self.width, self.height)
+            };
+            let scale = self.scale.max(1);
+            (width / scale, height / scale)
+        });
+        Monitor {
+            x,
+            y,
+            width,
+            height,
+            rotation: 0,
+        }
+    }
+
+    // the buffer comes back the way the output is scanned out, which the
+    // compositor got to by flipping and then rotating the layout counter
+    // clockwise. undone here in reverse
+    fn untransform(&self, image: image::RgbImage) -> image::RgbImage {
+        use image::imageops::{flip_horizontal, rotate180, rotate270, rotate90};
+        let rotated = match self.transform % 4 {
+            1 => rotate90(&image),
+            2 => rotate180(&image),
+            3 => rotate270(&image),
+            _ => image,
+        };
+        // 4 to 7 are the flipped variants
+        if self.transform >= 4 {
+            flip_horizontal(&rotated)
+        } else {
+            rotated
+        }
+    }
+}
+
+struct Globals {
+    shm: u32,
+    screencopy: u32,
+    outputs: Vec<Output>,
+}
+
+impl Globals {
+    fn bind(connection: &mut Connection) -> Result<Self, String> {
+        let registry = connection.new_id();
+        connection.send(DISPLAY, 1, &[Arg::Uint(registry)])?;
+
+        let mut advertised = Vec::new();
+        connection.roundtrip(|message| {
+            if message.object == registry && message.opcode == 0 {
+                let mut args = message.args();
+                let (name, interface, version) = (args.uint(), args.string(), args.uint());
+                advertised.push((name, interface, version));
+            }
+        })?;
+
+        let find = |wanted: &str| {
+            advertised
+                .iter()
+                .find(|(_, interface, _)| interface == wanted)
+                .map(|&(name, _, version)| (name, version))
+        };
+        let (shm_name, _) = find("wl_shm").ok_or("the compositor has no wl_shm")?;
+        let (screencopy_name, _) = find("zwlr_screencopy_manager_v1")
+            .ok_or("the compositor doesn't support wlr-screencopy")?;
+
+        let shm = connection.bind(registry, shm_name, "wl_shm", 1)?;
+        let screencopy =
+            connection.bind(registry, screencopy_name, "zwlr_screencopy_manager_v1", 1)?;
+
+        let mut outputs = Vec::new();
+        for (name, _, version) in advertised.iter().filter(|(_, i, _)| i == "wl_output") {
+            // version 2 adds the scale
+            let id = connection.bind(registry, *name, "wl_output", (*version).min(2))?;
+            outputs.push(Output {
+                id,
+                scale: 1,
+                ..Default::default()
+            });
+        }
+
+        // each output's zxdg_output_v1, matched back to it by position
+        let mut xdg_outputs = Vec::new();
+        if let Some((name, version)) = find("zxdg_output_manager_v1") {
+            let manager =
+                connection.bind(registry, name, "zxdg_output_manager_v1", version.min(3))?;
+            for output in &outputs {
+                let xdg_output = connection.new_id();
+                connection.send(manager, 1, &[Arg::Uint(xdg_output), Arg::Uint(output.id)])?;
+                xdg_outputs.push(xdg_output);
+            }
+        }
+
+        connection.roundtrip(|message| {
+            let mut args = message.args();
+            if let Some(index) = xdg_outputs.iter().position(|id| *id == message.object) {
+                let output = &mut outputs[index];
+                match message.opcode {
+                    0 => output.logical_position = Some((args.int(), args.int())),
+                    1 => output.logical_size = Some((args.int(), args.int())),
+                    _ => {}
+                }
+                return;
+            }
+
+            let Some(output) = outputs.iter_mut().find(|o| o.id == message.object) else {
+                return;
+            };
+            match message.opcode {
+                // geometry: x, y, physical size, subpixel, make, model, transform
+                0 => {
+                    (output.x, output.y) = (args.int(), args.int());
+                    for _ in 0..3 {
+                        args.uint();
+                    }
+                    args.string();
+                    args.string();
+                    output.transform = args.int();
+                }
+                // mode: flags, width, height, refresh. only the current one matters
+                1 if args.uint() & 1 != 0 => {
+                    (output.width, output.height) = (args.int(), args.int());
+                }
+                3 => output.scale = args.int(),
+                _ => {}
+            }
+        })?;
+
+        Ok(Self {
+            shm,
+            screencopy,
+            outputs,
+        })
+    }
+}
+
+struct Connection {
+    stream: UnixStream,
+    next_id: u32,
+    incoming: Vec<u8>,
+}
+
+impl Connection {
+    fn open() -> Result<Self, String> {
+        let name = std::env::var_os("WAYLAND_DISPLAY").ok_or("WAYLAND_DISPLAY isn't set")?;
+        let path = std::path::Path::new(&name);
+        let path = if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            let runtime = std::env::var_os("XDG_RUNTIME_DIR").ok_or("XDG_RUNTIME_DIR isn't set")?;
+            std::path::Path::new(&runtime).join(path)
+        };
+        let stream = UnixStream::connect(&path)
+            .map_err(|e| format!("could not connect to {}: {e}", path.display()))?;
+        Ok(Self {
+            stream,
+            next_id: DISPLAY + 1,
+            incoming: Vec::new(),
+        })
+    }
+
+    fn new_id(&mut self) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    fn encode(object: u32, opcode: u16, args: &[Arg]) -> Vec<u8> {
+        let mut message = vec![0; 8];
+        for arg in args {
+            match arg {
+                Arg::Uint(value) => message.extend(value.to_ne_bytes()),
+                Arg::Int(value) => message.extend(value.to_ne_bytes()),
+                Arg::Str(value) => {
+                    message.extend((value.len() as u32 + 1).to_ne_bytes());
+                    message.extend(value.as_bytes());
+                    message.resize(message.len() + 1, 0);
+                    message.resize(message.len().next_multiple_of(4), 0);
+                }
+            }
+        }
+        let header = ((message.len() as u32) << 16) | opcode as u32;
+        message[..4].copy_from_slice(&object.to_ne_bytes());
+        message[4..8].copy_from_slice(&header.to_ne_bytes());
+        message
+    }
+
+    fn send(&mut self, object: u32, opcode: u16, args: &[Arg]) -> Result<(), String> {
+        self.stream
+            .write_all(&Self::encode(object, opcode, args))
+            .map_err(|e| format!("wayland: {e}"))
+    }
+
+    // fds travel next to the message as ancillary data, not in its body
+    fn send_with_fd(
+        &mut self,
+        object: u32,
+        opcode: u16,
+        args: &[Arg],
+        fd: RawFd,
+    ) -> Result<(), String> {
+        let message = Self::encode(object, opcode, args);
+        let mut iov = libc::iovec {
+            iov_base: message.as_ptr() as *mut libc::c_void,
+            iov_len: message.len(),
+        };
+        let fd_size = std::mem::size_of::<RawFd>() as u32;
+        let mut control = vec![0u8; unsafe { libc::CMSG_SPACE(fd_size) } as usize];
+
+        let sent = unsafe {
+            let mut header: libc::msghdr = std::mem::zeroed();
+            header.msg_iov = &mut iov;
+            header.msg_iovlen = 1;
+            header.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+            header.msg_controllen = control.len() as _;
+
+            let cmsg = libc::CMSG_FIRSTHDR(&header);
+            (*cmsg).cmsg_level = libc::SOL_SOCKET;
+            (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+            (*cmsg).cmsg_len = libc::CMSG_LEN(fd_size) as _;
+            std::ptr::write_unaligned(libc::CMSG_DATA(cmsg) as *mut RawFd, fd);
+
+            libc::sendmsg(self.stream.as_raw_fd(), &header, 0)
+        };
+        if sent != message.len() as isize {
+            return Err(format!("wayland: {}", std::io::Error::last_os_error()));
+        }
+        Ok(())
+    }
+
+    fn bind(
+        &mut self,
+        registry: u32,
+        name: u32,
+        interface: &str,
+        version: u32,
+    ) -> Result<u32, String> {
+        let id = self.new_id();
+        self.send(
+            registry,
+            0,
+            &[
+                Arg::Uint(name),
+                Arg::Str(interface),
+                Arg::Uint(version),
+                Arg::Uint(id),
+            ],
+        )?;
+        Ok(id)
+    }
+
+    fn next_event(&mut self) -> Result<Message, String> {
+        loop {
+            if self.incoming.len() >= 8 {
+                let size =
+                    (u32::from_ne_bytes(self.incoming[4..8].try_into().unwrap()) >> 16) as usize;
+                if size >= 8 && self.incoming.len() >= size {
+                    let bytes: Vec<u8> = self.incoming.drain(..size).collect();
+                    let message = Message {
+                        object: u32::from_ne_bytes(bytes[..4].try_into().unwrap()),
+                        opcode: u16::from_ne_bytes(bytes[4..6].try_into().unwrap()),
+                        body: bytes[8..].to_vec(),
+                    };
+                    // wl_display.error: object, code, message. always fatal
+                    if message.object == DISPLAY && message.opcode == 0 {
+                        let mut args = message.args();
+                        let (_, code, text) = (args.uint(), args.uint(), args.string());
+                        return Err(format!("compositor error {code}: {text}"));
+                    }
+                    return Ok(message);
+                }
+            }
+
+            let mut buffer = [0; 4096];
+            let read = self
+                .stream
+                .read(&mut buffer)
+                .map_err(|e| format!("wayland: {e}"))?;
+            if read == 0 {
+                return Err("the compositor closed the connection".to_owned());
+            }
+            self.incoming.extend_from_slice(&buffer[..read]);
+        }
+    }
+
+    // every event up to a wl_display.sync, which the compositor answers only
+    // once it has handled all the requests before it
+    fn roundtrip(&mut self, mut handle: impl FnMut(&Message)) -> Result<(), String> {
+        let callback = self.new_id();
+        self.send(DISPLAY, 0, &[Arg::Uint(callback)])?;
+        loop {
+            let message = self.next_event()?;
+            if message.object == callback {
+                return Ok(());
+            }
+            handle(&message);
+        }
+    }
+
+    // region is in the output's layout coordinates, relative to its corner
+    fn capture_region(
+        &mut self,
+        globals: &Globals,
+        output: u32,
+        region: Rect,
+        cursor: bool,
+    ) -> Result<image::RgbImage, String> {
+        let frame = self.new_id();
+        self.send(
+            globals.screencopy,
+            1,
+            &[
+                Arg::Uint(frame),
+                Arg::Int(cursor as i32),
+                Arg::Uint(output),
+                Arg::Int(region.x),
+                Arg::Int(region.y),
+                Arg::Int(region.width),
+                Arg::Int(region.height),
+            ],
+        )?;
+
+        // buffer: format, width, height, stride
+        let (format, width, height, stride) = loop {
+            let message = self.next_event()?;
+            if message.object != frame {
+                continue;
+            }
+            let mut args = message.args();
+            match message.opcode {
+                0 => break (args.uint(), args.uint(), args.uint(), args.uint()),
+                3 => return Err("the compositor refused the capture".to_owned()),
+                _ => {}
+            }
+        };
+
+        let size = stride as usize * height as usize;
+        let file = memfd()?;
+        file.set_len(size as u64)
+            .map_err(|e| format!("wayland: {e}"))?;
+
+        let pool = self.new_id();
+        self.send_with_fd(
+            globals.shm,
+            0,
+            &[Arg::Uint(pool), Arg::Int(size as i32)],
+            file.as_raw_fd(),
+        )?;
+        let buffer = self.new_id();
+        self.send(
+            pool,
+            0,
+            &[
+                Arg::Uint(buffer),
+                Arg::Int(0),
+                Arg::Int(width as i32),
+                Arg::Int(height as i32),
+                Arg::Int(stride as i32),
+                Arg::Uint(format),
+            ],
+        )?;
+        self.send(frame, 0, &[Arg::Uint(buffer)])?;
+
+        let mut y_invert = false;
+        let result = loop {
+            let message = self.next_event()?;
+            if message.object != frame {
+                continue;
+            }
+            match message.opcode {
+                1 => y_invert = message.args().uint() & 1 != 0,
+                2 => break Ok(()),
+                3 => break Err("the compositor failed to copy the screen".to_owned()),
+                _ => {}
+            }
+        };
+
+        // buffer, pool and frame all have a destroy request
+        self.send(buffer, 0, &[])?;
+        self.send(pool, 1, &[])?;
+        self.send(frame, 1, &[])?;
+        result?;
+
+        let mut data = vec![0; size];
+        file.read_exact_at(&mut data, 0)
+            .map_err(|e| format!("wayland: {e}"))?;
+
+        let mut image = decode(&data, format, width, height, stride)?;
+        if y_invert {
+            image::imageops::flip_vertical_in_place(&mut image);
+        }
+        Ok(image)
+    }
+}
+
+fn memfd() -> Result<std::fs::File, String> {
+    let fd = unsafe { libc::memfd_create(c"sleek-screencopy".as_ptr(), libc::MFD_CLOEXEC) };
+    if fd < 0 {
+        return Err(format!("wayland: {}", std::io::Error::last_os_error()));
+    }
+    Ok(unsafe { std::fs::File::from_raw_fd(fd) })
+}
+
+// the formats are named most significant byte first and stored little endian,
+// so ARGB8888 is B, G, R, A in memory
+fn decode(
+    data: &[u8],
+    format: u32,
+    width: u32,
+    height: u32,
+    stride: u32,
+) -> Result<image::RgbImage, String> {
+    let (r, g, b) = match format {
+        ARGB8888 | XRGB8888 => (2, 1, 0),
+        ABGR8888 | XBGR8888 => (0, 1, 2),
+        _ => return Err(format!("unsupported screencopy format {format:#x}")),
+    };
+    let pixels = data
+        .chunks_exact(stride as usize)
+        .take(height as usize)
+        .flat_map(|row| {
+            row[..width as usize * 4]
+                .chunks_exact(4)
+                .flat_map(|pixel| [pixel[r], pixel[g], pixel[b]])
+        })
+        .collect();
+    image::RgbImage::from_raw(width, height, pixels)
+        .ok_or_else(|| "the screencopy buffer is too small".to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a single pixel of 1, 2, 3 in each format's byte order, with the rows
+    // padded out past width * 4
+    #[test]
+    fn decodes_every_format() {
+        for (format, pixel) in [
+            (ARGB8888, [3, 2, 1, 255]),
+            (XRGB8888, [3, 2, 1, 0]),
+            (ABGR8888, [1, 2, 3, 255]),
+            (XBGR8888, [1, 2, 3, 0]),
+        ] {
+            let data = [pixel, [9; 4], pixel, [9; 4]].concat();
+            let image = decode(&data, format, 1, 2, 8).unwrap();
+            assert_eq!(image.into_raw(), [1, 2, 3, 1, 2, 3], "format {format:#x}");
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_formats_and_short_buffers() {
+        assert!(decode(&[0; 4], 0x3231_5258, 1, 1, 4).is_err());
+        assert!(decode(&[0; 4], XRGB8888, 1, 2, 4).is_err());
+    }
+
+    // what the compositor hands back for a layout row of 1, 2, 3 under each
+    // transform, as (width, height, pixels)
+    #[test]
+    fn undoes_every_transform() {
+        let buffers: [(u32, u32, [u8; 3]); 8] = [
+            (3, 1, [1, 2, 3]),
+            (1, 3, [3, 2, 1]),
+            (3, 1, [3, 2, 1]),
+            (1, 3, [1, 2, 3]),
+            (3, 1, [3, 2, 1]),
+            (1, 3, [1, 2, 3]),
+            (3, 1, [1, 2, 3]),
+            (1, 3, [3, 2, 1]),
+        ];
+        for (transform, (width, height, pixels)) in buffers.into_iter().enumerate() {
+            let buffer = image::RgbImage::from_fn(width, height, |x, y| {
+                image::Rgb([pixels[(x + y) as usize], 0, 0])
+            });
+            let output = Output {
+                transform: transform as i32,
+                ..Default::default()
+            };
+            let layout = output.untransform(buffer);
+            assert_eq!(layout.dimensions(), (3, 1), "transform {transform}");
+            let row: Vec<u8> = layout.pixels().map(|pixel| pixel[0]).collect();
+            assert_eq!(row, [1, 2, 3], "transform {transform}");
+        }
+    }
+
+    #[test]
+    fn logical_size_comes_from_the_mode_and_scale() {
+        let output = Output {
+            x: 100,
+            y: 50,
+            width: 3840,
+            height: 2160,
+            scale: 2,
+            ..Default::default()
+        };
+        let logical = output.logical();
+        assert_eq!(
+            (logical.x, logical.y, logical.width, logical.height),
+            (100, 50, 1920, 1080)
+        );
+
+        let rotated = Output {
+            transform: 1,
+            ..output
+        };
+        let logical = rotated.logical();
+        assert_eq!((logical.width, logical.height), (1080, 1920));
+    }
+
+    #[test]
+    fn xdg_output_overrides_the_mode() {
+        // 3840x2160 at a scale of 1.5, which wl_output can only call 2
+        let output = Output {
+            x: 0,
+            y: 0,
+            width: 3840,
+            height: 2160,
+            scale: 2,
+            logical_position: Some((1920, 0)),
+            logical_size: Some((2560, 1440)),
+            ..Default::default()
+        };
+        let logical = output.logical();
+        assert_eq!(
+            (logical.x, logical.y, logical.width, logical.height),
+            (1920, 0, 2560, 1440)
+        );
+    }
+}
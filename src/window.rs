@@ -0,0 +1,429 @@
+use crate::monitor::{self, Monitor};
+use crate::sidecar::WindowInfo;
+use crate::{x11_safe, Point, Rect, ScreenData};
+use std::time::{Duration, Instant};
+use x11::xlib::{IsViewable, PointerRoot};
+
+const PID_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// climb from a (possibly nested) window up to the child of the root that contains it
+pub fn top_level(display: x11_safe::Display, window: u64) -> u64 {
+    let mut window = window;
+
+    loop {
+        let Ok((root, parent)) = x11_safe::query_tree(display, window) else {
+            return window;
+        };
+
+        if parent == root || parent == 0 {
+            return window;
+        }
+        window = parent;
+    }
+}
+
+// every visible child of the root, bottom to top. with a reparenting window
+// manager these are the frames, so the rects include the decorations
+pub fn top_level_rects(display: x11_safe::Display, root: u64) -> Vec<Rect> {
+    x11_safe::children(display, root)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|window| {
+            x11_safe::get_window_attributes(display, *window)
+                .is_ok_and(|attributes| attributes.map_state == IsViewable)
+        })
+        .filter_map(|window| geometry(display, window))
+        .collect()
+}
+
+// the topmost of the rects containing point
+pub fn rect_at(rects: &[Rect], point: Point) -> Option<Rect> {
+    rects.iter().rev().copied().find(|rect| {
+        point.x >= rect.x
+            && point.y >= rect.y
+            && point.x < rect.x + rect.width
+            && point.y < rect.y + rect.height
+    })
+}
+
+// moves each coordinate of point onto the nearest window edge within
+// threshold, only counting edges that point is alongside
+pub fn snap(rects: &[Rect], point: Point, threshold: i32) -> Point {
+    let nearest = |value: i32, edges: &mut dyn Iterator<Item = i32>| {
+        edges
+            .map(|edge| (edge, (edge - value).abs()))
+            .filter(|(_, distance)| *distance <= threshold)
+            .min_by_key(|(_, distance)| *distance)
+            .map_or(value, |(edge, _)| edge)
+    };
+
+    let x = nearest(
+        point.x,
+        &mut rects
+            .iter()
+            .filter(|rect| point.y >= rect.y && point.y <= rect.y + rect.height)
+            .flat_map(|rect| [rect.x, rect.x + rect.width]),
+    );
+    let y = nearest(
+        point.y,
+        &mut rects
+            .iter()
+            .filter(|rect| point.x >= rect.x && point.x <= rect.x + rect.width)
+            .flat_map(|rect| [rect.y, rect.y + rect.height]),
+    );
+
+    Point::new(x, y)
+}
+
+// position relative to the root window plus size
+pub fn geometry(display: x11_safe::Display, window: u64) -> Option<Rect> {
+    let geometry = x11_safe::get_geometry(display, window).ok()?;
+    let (x, y) = x11_safe::translate_coordinates(display, window, geometry.root, 0, 0).ok()?;
+
+    Some(Rect::new(
+        x,
+        y,
+        geometry.width as i32,
+        geometry.height as i32,
+    ))
+}
+
+// negative offsets are measured from the right/bottom edge of the window
+pub fn relative_to_active(offset: Rect) -> Result<(Point, Point), String> {
+    let connection = x11_safe::open_display().map_err(|e| e.to_string())?;
+    let display = connection.display();
+
+    let focus = x11_safe::get_input_focus(display);
+    let window = if focus == 0 || focus == PointerRoot as u64 {
+        None
+    } else {
+        geometry(display, top_level(display, focus))
+    };
+
+    let window = window.ok_or_else(|| "no active window".to_owned())?;
+
+    let x = if offset.x < 0 {
+        window.width + offset.x
+    } else {
+        offset.x
+    };
+    let y = if offset.y < 0 {
+        window.height + offset.y
+    } else {
+        offset.y
+    };
+
+    let min = Point::new(window.x + x, window.y + y);
+    Ok((min, Point::new(min.x + offset.width, min.y + offset.height)))
+}
+
+// polls the window manager's client list until a visible window owned by pid
+// shows up, taking the oldest one if the process has several
+pub fn wait_for_pid(pid: u32, timeout: Option<Duration>) -> Result<u64, String> {
+    let connection = x11_safe::open_display().map_err(|e| e.to_string())?;
+    let display = connection.display();
+    let root = x11_safe::root_window(display, x11_safe::default_screen(display));
+
+    let start = Instant::now();
+    loop {
+        if let Some(window) = window_for_pid(display, root, pid) {
+            break Ok(window);
+        }
+
+        if timeout.is_some_and(|timeout| start.elapsed() >= timeout) {
+            break Err(format!("timed out waiting for a window from pid {pid}"));
+        }
+        std::thread::sleep(PID_POLL_INTERVAL);
+    }
+}
+
+fn window_for_pid(display: x11_safe::Display, root: u64, pid: u32) -> Option<u64> {
+    x11_safe::window_property(display, root, "_NET_CLIENT_LIST")?
+        .into_iter()
+        .find(|window| {
+            let owner = x11_safe::cardinal_property(display, *window, "_NET_WM_PID");
+            owner.as_deref() == Some(&[pid as u64])
+                && x11_safe::get_window_attributes(display, *window)
+                    .is_ok_and(|attributes| attributes.map_state == IsViewable)
+        })
+}
+
+// the window manager's idea of the active window, None when nothing is
+// active. the frame is included like every other window capture unless
+// client_only asks for just the application's own window
+pub fn active_window(client_only: bool) -> Result<Option<Rect>, String> {
+    let connection = x11_safe::open_display().map_err(|e| e.to_string())?;
+    let display = connection.display();
+    let root = x11_safe::root_window(display, x11_safe::default_screen(display));
+
+    let active = x11_safe::window_property(display, root, "_NET_ACTIVE_WINDOW")
+        .and_then(|windows| windows.first().copied())
+        .filter(|window| *window != 0);
+    let rect = active.and_then(|window| {
+        if client_only {
+            geometry(display, window)
+        } else {
+            geometry(display, top_level(display, window))
+        }
+    });
+
+    Ok(rect)
+}
+
+// grabs the window's own contents instead of the root, so an ARGB window keeps
+// its alpha rather than being composited against whatever is behind it. with
+// decorations the frame belongs to the window manager, so that has to come
+// from the root instead
+pub struct Capture {
+    pub image: image::DynamicImage,
+    pub geometry: Rect,
+    pub monitor: Option<Monitor>,
+    pub info: Option<WindowInfo>,
+}
+
+pub fn capture_window(window: u64, client_only: bool) -> Result<Capture, String> {
+    let connection = x11_safe::open_display().map_err(|e| e.to_string())?;
+    let display = connection.display();
+    capture_window_contents(display, window, client_only)
+}
+
+fn capture_window_contents(
+    display: x11_safe::Display,
+    window: u64,
+    client_only: bool,
+) -> Result<Capture, String> {
+    let attributes = x11_safe::get_window_attributes(display, window)
+        .map_err(|_| format!("no window with id {window:#x}"))?;
+    if attributes.map_state != IsViewable {
+        return Err(format!("window {window:#x} is not visible"));
+    }
+
+    let root = x11_safe::get_geometry(display, window)
+        .map_err(|e| e.to_string())?
+        .root;
+    let client = geometry(display, window).ok_or("window has no geometry")?;
+
+    let (image, geometry) = match frame_rect(display, window, client) {
+        Some(frame) if !client_only => capture_root_rect(display, root, frame)?,
+        _ => {
+            // like the root, XGetImage on a window fails if any of the rect
+            // is off screen
+            let root_attributes =
+                x11_safe::get_window_attributes(display, root).map_err(|e| e.to_string())?;
+            let screen = Rect::new(0, 0, root_attributes.width, root_attributes.height);
+            let visible = client.intersect(&screen).ok_or("entirely off screen")?;
+
+            let image = x11_safe::get_image(
+                display,
+                window,
+                visible.x - client.x,
+                visible.y - client.y,
+                visible.width as u32,
+                visible.height as u32,
+            )
+            .map_err(|e| e.to_string())?;
+            let capture = decode_image(&image, &attributes);
+            (capture, visible)
+        }
+    };
+
+    let centre = Point::new(
+        geometry.x + geometry.width / 2,
+        geometry.y + geometry.height / 2,
+    );
+
+    Ok(Capture {
+        image,
+        geometry,
+        monitor: monitor::monitor_at(display, root, centre),
+        info: Some(WindowInfo {
+            title: x11_safe::fetch_name(display, window),
+            class: x11_safe::class_hint(display, window),
+        }),
+    })
+}
+
+// a rect of the root in root coordinates, cut down to the part that's on screen
+pub fn capture_rect(rect: Rect) -> Result<Capture, String> {
+    let connection = x11_safe::open_display().map_err(|e| e.to_string())?;
+    let display = connection.display();
+    let root = x11_safe::root_window(display, x11_safe::default_screen(display));
+
+    capture_root_rect(display, root, rect).map(|(image, geometry)| Capture {
+        image,
+        geometry,
+        monitor: monitor::monitor_at(
+            display,
+            root,
+            Point::new(
+                geometry.x + geometry.width / 2,
+                geometry.y + geometry.height / 2,
+            ),
+        ),
+        info: None,
+    })
+}
+
+// the client rect grown by _NET_FRAME_EXTENTS (left, right, top, bottom), None
+// when the window manager doesn't decorate this window
+fn frame_rect(display: x11_safe::Display, window: u64, client: Rect) -> Option<Rect> {
+    let extents = x11_safe::cardinal_property(display, window, "_NET_FRAME_EXTENTS")
+        .filter(|extents| extents.len() == 4 && extents.iter().any(|e| *e != 0))?;
+    let (left, right, top, bottom) = (
+        extents[0] as i32,
+        extents[1] as i32,
+        extents[2] as i32,
+        extents[3] as i32,
+    );
+
+    Some(Rect::new(
+        client.x - left,
+        client.y - top,
+        client.width + left + right,
+        client.height + top + bottom,
+    ))
+}
+
+fn capture_root_rect(
+    display: x11_safe::Display,
+    root: u64,
+    rect: Rect,
+) -> Result<(image::DynamicImage, Rect), String> {
+    let attributes = x11_safe::get_window_attributes(display, root).map_err(|e| e.to_string())?;
+
+    // XGetImage fails outright if any of the rect is off screen
+    let (min, max) = rect.corners();
+    let min = min.max(&Point::new(0, 0));
+    let max = max.min(&Point::new(attributes.width, attributes.height));
+    if max.x <= min.x || max.y <= min.y {
+        return Err("entirely off screen".to_owned());
+    }
+
+    let image = x11_safe::get_image(
+        display,
+        root,
+        min.x,
+        min.y,
+        (max.x - min.x) as u32,
+        (max.y - min.y) as u32,
+    )
+    .map_err(|e| e.to_string())?;
+    let capture = decode_image(&image, &attributes);
+
+    Ok((
+        capture,
+        Rect::new(min.x, min.y, max.x - min.x, max.y - min.y),
+    ))
+}
+
+fn decode_image(
+    image: &x11_safe::Image,
+    attributes: &x11_safe::WindowAttributes,
+) -> image::DynamicImage {
+    let (width, height) = (image.width() as u32, image.height() as u32);
+    let screen_data = ScreenData::new(
+        width as i32,
+        height as i32,
+        attributes.depth as u32,
+        attributes.red_mask,
+        attributes.green_mask,
+        attributes.blue_mask,
+    );
+
+    if screen_data.has_alpha() {
+        let pixels = image
+            .pixels()
+            .iter()
+            .flat_map(|p| screen_data.decode_rgba(*p))
+            .collect();
+        image::DynamicImage::ImageRgba8(image::RgbaImage::from_raw(width, height, pixels).unwrap())
+    } else {
+        let pixels = image
+            .pixels()
+            .iter()
+            .flat_map(|p| {
+                let colour = screen_data.decode(*p);
+                [colour.r, colour.g, colour.b]
+            })
+            .collect();
+        image::DynamicImage::ImageRgb8(image::RgbImage::from_raw(width, height, pixels).unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW: Rect = Rect {
+        x: 100,
+        y: 100,
+        width: 50,
+        height: 50,
+    };
+
+    #[test]
+    fn snaps_within_the_threshold_inclusive() {
+        assert_eq!(
+            snap(&[WINDOW], Point::new(92, 120), 8),
+            Point::new(100, 120)
+        );
+        assert_eq!(snap(&[WINDOW], Point::new(91, 120), 8), Point::new(91, 120));
+        assert_eq!(
+            snap(&[WINDOW], Point::new(158, 120), 8),
+            Point::new(150, 120)
+        );
+        assert_eq!(
+            snap(&[WINDOW], Point::new(120, 108), 8),
+            Point::new(120, 100)
+        );
+    }
+
+    #[test]
+    fn only_snaps_to_edges_alongside_the_point() {
+        // level with the window's left edge in x but well below it
+        assert_eq!(snap(&[WINDOW], Point::new(98, 300), 8), Point::new(98, 300));
+        // just outside the corner it's alongside neither edge
+        assert_eq!(snap(&[WINDOW], Point::new(96, 154), 8), Point::new(96, 154));
+        // just inside it's alongside both
+        assert_eq!(
+            snap(&[WINDOW], Point::new(104, 146), 8),
+            Point::new(100, 150)
+        );
+    }
+
+    #[test]
+    fn snaps_to_the_nearest_edge() {
+        let next_door = Rect::new(154, 100, 50, 50);
+        assert_eq!(
+            snap(&[WINDOW, next_door], Point::new(153, 120), 8),
+            Point::new(154, 120)
+        );
+        assert_eq!(
+            snap(&[WINDOW, next_door], Point::new(151, 120), 8),
+            Point::new(150, 120)
+        );
+    }
+
+    #[test]
+    fn rect_at_takes_the_topmost() {
+        let above = Rect::new(120, 120, 50, 50);
+        assert_eq!(rect_at(&[WINDOW, above], Point::new(130, 130)), Some(above));
+        assert_eq!(
+            rect_at(&[WINDOW, above], Point::new(110, 110)),
+            Some(WINDOW)
+        );
+        assert_eq!(
+            rect_at(&[above, WINDOW], Point::new(130, 130)),
+            Some(WINDOW)
+        );
+    }
+
+    #[test]
+    fn rect_at_excludes_the_far_edges() {
+        assert_eq!(rect_at(&[WINDOW], Point::new(100, 100)), Some(WINDOW));
+        assert_eq!(rect_at(&[WINDOW], Point::new(149, 149)), Some(WINDOW));
+        assert_eq!(rect_at(&[WINDOW], Point::new(150, 120)), None);
+        assert_eq!(rect_at(&[WINDOW], Point::new(120, 150)), None);
+        assert_eq!(rect_at(&[], Point::new(120, 120)), None);
+    }
+}
@@ -0,0 +1,57 @@
+use crate::args::Args;
+use crate::error::SleekError;
+use crate::x11_safe::{self, Event};
+use crate::{draw_selection, save_selection, window, Point, Rect, RenderWindow};
+use x11::xlib::Button1;
+
+// the window list was taken before the overlay went up, so it is searched
+// instead of asking the server what is under the pointer (which is the overlay)
+fn window_at(render_window: &RenderWindow, x: i32, y: i32) -> Option<(Point, Point)> {
+    let origin = render_window.origin;
+    let rect = window::rect_at(
+        &render_window.windows,
+        Point::new(x + origin.x, y + origin.y),
+    )?;
+
+    // in overlay coordinates and cut down to what is actually on screen
+    let screen = Point::new(
+        render_window.screen_data.width,
+        render_window.screen_data.height,
+    );
+    let Rect {
+        x,
+        y,
+        width,
+        height,
+    } = rect;
+    let min = Point::new(x - origin.x, y - origin.y).max(&Point::new(0, 0));
+    let max = Point::new(x - origin.x + width, y - origin.y + height).min(&screen);
+    Some((min, max))
+}
+
+// highlights whichever window is under the pointer, a click saves it
+pub fn pick_window(render_window: &mut RenderWindow, args: &Args) -> Result<(), SleekError> {
+    let mut highlighted = None;
+
+    loop {
+        match x11_safe::next_event(render_window.display) {
+            Event::Motion { x, y } => {
+                let under = window_at(render_window, x, y);
+                if under != highlighted {
+                    highlighted = under;
+                    match under {
+                        Some((min, max)) => draw_selection(render_window, args, min, max),
+                        None => render_window.clear(),
+                    }
+                }
+            }
+            Event::ButtonPress { button, x, y, .. } if button == Button1 => {
+                if let Some((min, max)) = window_at(render_window, x, y) {
+                    return save_selection(render_window, args, min, max);
+                }
+            }
+            Event::KeyPress { keysym, .. } if args.is_cancel(keysym) => return Ok(()),
+            _ => {}
+        }
+    }
+}
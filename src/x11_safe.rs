@@ -0,0 +1,1458 @@
+// safe-ish wrappers around the Xlib calls sleek makes, all of the unsafe and
+// the null/error checking on what comes back from the server lives in here
+use std::ffi::CString;
+use std::mem::MaybeUninit;
+use std::ptr::NonNull;
+use x11::xfixes::*;
+use x11::xlib::*;
+use x11::xrandr::*;
+
+// a handle on an open connection. it can only come from a Connection, so the
+// functions here never see a pointer that wasn't handed out by XOpenDisplay
+#[derive(Copy, Clone)]
+pub struct Display(NonNull<_XDisplay>);
+
+impl Display {
+    fn raw(self) -> *mut _XDisplay {
+        self.0.as_ptr()
+    }
+}
+
+// owns the connection and closes it when dropped. anything made on it (fonts,
+// windows, GCs) has to be freed before then
+pub struct Connection(Display);
+
+impl Connection {
+    pub fn display(&self) -> Display {
+        self.0
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        unsafe {
+            XCloseDisplay(self.0.raw());
+        }
+    }
+}
+
+// same for graphics contexts, only create_gc and create_default_gc make one
+#[derive(Copy, Clone)]
+pub struct Gc(NonNull<_XGC>);
+
+impl Gc {
+    fn raw(self) -> *mut _XGC {
+        self.0.as_ptr()
+    }
+}
+
+#[derive(Debug)]
+pub struct Error(&'static str);
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} failed", self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+// X names can't hold a NUL, so anything after one would never have been seen
+// by the server anyway
+fn c_string(s: &str) -> CString {
+    let end = s.find('\0').unwrap_or(s.len());
+    CString::new(&s[..end]).unwrap_or_default()
+}
+
+pub fn open_display() -> Result<Connection, Error> {
+    let display = unsafe { XOpenDisplay(std::ptr::null()) };
+    NonNull::new(display)
+        .map(|display| Connection(Display(display)))
+        .ok_or(Error("XOpenDisplay"))
+}
+
+pub fn default_screen(display: Display) -> i32 {
+    unsafe { XDefaultScreen(display.raw()) }
+}
+
+pub fn screen_count(display: Display) -> i32 {
+    unsafe { XScreenCount(display.raw()) }
+}
+
+// horizontal dots per inch from the size the server reports for the screen,
+// None when it doesn't know the physical size
+pub fn dpi(display: Display, screen_number: i32) -> Option<f64> {
+    let (pixels, millimetres) = unsafe {
+        (
+            XDisplayWidth(display.raw(), screen_number),
+            XDisplayWidthMM(display.raw(), screen_number),
+        )
+    };
+    (pixels > 0 && millimetres > 0).then(|| pixels as f64 * 25.4 / millimetres as f64)
+}
+
+pub fn root_window(display: Display, screen_number: i32) -> u64 {
+    unsafe { XRootWindow(display.raw(), screen_number) }
+}
+
+pub fn display_size(display: Display, screen_number: i32) -> (i32, i32) {
+    unsafe {
+        (
+            XDisplayWidth(display.raw(), screen_number),
+            XDisplayHeight(display.raw(), screen_number),
+        )
+    }
+}
+
+pub fn default_depth(display: Display, screen_number: i32) -> i32 {
+    unsafe { XDefaultDepth(display.raw(), screen_number) }
+}
+
+// TrueColor, PseudoColor, ... of the screen's default visual
+pub fn default_visual_class(display: Display, screen_number: i32) -> i32 {
+    unsafe { (*XDefaultVisual(display.raw(), screen_number)).class }
+}
+
+pub fn query_extension(display: Display, name: &str) -> bool {
+    let name = c_string(name);
+    let (mut opcode, mut event, mut error) = (0, 0, 0);
+    unsafe {
+        XQueryExtension(
+            display.raw(),
+            name.as_ptr(),
+            &mut opcode,
+            &mut event,
+            &mut error,
+        ) != 0
+    }
+}
+
+pub fn intern_atom(display: Display, name: &str) -> u64 {
+    let name = c_string(name);
+    unsafe { XInternAtom(display.raw(), name.as_ptr(), False) }
+}
+
+// ownership can be refused, so check it actually took
+pub fn set_selection_owner(display: Display, selection: u64, window: u64) -> Result<(), Error> {
+    unsafe {
+        XSetSelectionOwner(display.raw(), selection, window, CurrentTime);
+        if XGetSelectionOwner(display.raw(), selection) != window {
+            return Err(Error("XSetSelectionOwner"));
+        }
+    }
+    Ok(())
+}
+
+pub fn change_property_bytes(
+    display: Display,
+    window: u64,
+    property: u64,
+    property_type: u64,
+    data: &[u8],
+) {
+    unsafe {
+        XChangeProperty(
+            display.raw(),
+            window,
+            property,
+            property_type,
+            8,
+            PropModeReplace,
+            data.as_ptr(),
+            data.len() as i32,
+        );
+    }
+}
+
+// INCR in place of the data, a transfer of size bytes is about to follow in
+// pieces
+pub fn change_property_incr(display: Display, window: u64, property: u64, size: usize) {
+    let incr = intern_atom(display, "INCR");
+    let size: std::os::raw::c_ulong = size as _;
+    unsafe {
+        XChangeProperty(
+            display.raw(),
+            window,
+            property,
+            incr,
+            32,
+            PropModeReplace,
+            &size as *const _ as *const u8,
+            1,
+        );
+    }
+}
+
+// the most that fits in one ChangeProperty, the largest request the server
+// takes less the request's own header
+pub fn max_property_bytes(display: Display) -> usize {
+    let words = unsafe {
+        match XExtendedMaxRequestSize(display.raw()) {
+            // the server doesn't do BIG-REQUESTS
+            0 => XMaxRequestSize(display.raw()),
+            words => words,
+        }
+    };
+    (words as usize * 4).saturating_sub(28)
+}
+
+pub fn change_property_atoms(display: Display, window: u64, property: u64, atoms: &[u64]) {
+    // format 32 data is passed as an array of longs
+    let atoms: Vec<std::os::raw::c_ulong> = atoms.iter().map(|atom| *atom as _).collect();
+    unsafe {
+        XChangeProperty(
+            display.raw(),
+            window,
+            property,
+            XA_ATOM,
+            32,
+            PropModeReplace,
+            atoms.as_ptr() as *const u8,
+            atoms.len() as i32,
+        );
+    }
+}
+
+// property is None (0) when the request was refused
+pub fn send_selection_notify(display: Display, request: &SelectionRequest, property: u64) {
+    unsafe {
+        let mut event: XEvent = MaybeUninit::zeroed().assume_init();
+        event.selection = XSelectionEvent {
+            type_: x11::xlib::SelectionNotify,
+            serial: 0,
+            send_event: True,
+            display: display.raw(),
+            requestor: request.requestor,
+            selection: request.selection,
+            target: request.target,
+            property,
+            time: request.time,
+        };
+        XSendEvent(display.raw(), request.requestor, False, 0, &mut event);
+        XFlush(display.raw());
+    }
+}
+
+// the window currently holding a selection such as _NET_WM_CM_S0
+pub fn selection_owner(display: Display, name: &str) -> Option<u64> {
+    let name = c_string(name);
+    unsafe {
+        let atom = XInternAtom(display.raw(), name.as_ptr(), False);
+        match XGetSelectionOwner(display.raw(), atom) {
+            0 => None,
+            owner => Some(owner),
+        }
+    }
+}
+
+pub struct Geometry {
+    pub root: u64,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub border_width: u32,
+    pub depth: u32,
+}
+
+pub fn get_geometry(display: Display, drawable: u64) -> Result<Geometry, Error> {
+    let mut geometry = Geometry {
+        root: 0,
+        x: 0,
+        y: 0,
+        width: 0,
+        height: 0,
+        border_width: 0,
+        depth: 0,
+    };
+
+    let status = unsafe {
+        XGetGeometry(
+            display.raw(),
+            drawable,
+            &mut geometry.root,
+            &mut geometry.x,
+            &mut geometry.y,
+            &mut geometry.width,
+            &mut geometry.height,
+            &mut geometry.border_width,
+            &mut geometry.depth,
+        )
+    };
+
+    if status == 0 || status == BadDrawable as i32 {
+        return Err(Error("XGetGeometry"));
+    }
+    Ok(geometry)
+}
+
+// where the pointer is on window's root, None when it's on another screen
+pub fn query_pointer(display: Display, window: u64) -> Option<(i32, i32)> {
+    let (mut root, mut child) = (0, 0);
+    let (mut root_x, mut root_y, mut window_x, mut window_y) = (0, 0, 0, 0);
+    let mut mask = 0;
+    let same_screen = unsafe {
+        XQueryPointer(
+            display.raw(),
+            window,
+            &mut root,
+            &mut child,
+            &mut root_x,
+            &mut root_y,
+            &mut window_x,
+            &mut window_y,
+            &mut mask,
+        )
+    };
+    (same_screen != 0).then_some((root_x, root_y))
+}
+
+pub struct WindowAttributes {
+    pub width: i32,
+    pub height: i32,
+    pub depth: i32,
+    pub red_mask: u32,
+    pub green_mask: u32,
+    pub blue_mask: u32,
+    pub map_state: i32,
+}
+
+pub fn get_window_attributes(display: Display, window: u64) -> Result<WindowAttributes, Error> {
+    unsafe {
+        let mut attributes: XWindowAttributes = MaybeUninit::zeroed().assume_init();
+        if XGetWindowAttributes(display.raw(), window, &mut attributes) == 0
+            || attributes.visual.is_null()
+        {
+            return Err(Error("XGetWindowAttributes"));
+        }
+
+        Ok(WindowAttributes {
+            width: attributes.width,
+            height: attributes.height,
+            depth: attributes.depth,
+            red_mask: (*attributes.visual).red_mask as u32,
+            green_mask: (*attributes.visual).green_mask as u32,
+            blue_mask: (*attributes.visual).blue_mask as u32,
+            map_state: attributes.map_state,
+        })
+    }
+}
+
+// everything the screen offers, XMatchVisualInfo only hands back one match
+// per depth and class
+pub fn visuals(display: Display, screen_number: i32) -> Vec<XVisualInfo> {
+    unsafe {
+        let mut template: XVisualInfo = MaybeUninit::zeroed().assume_init();
+        template.screen = screen_number;
+        let mut count = 0;
+        let list = XGetVisualInfo(display.raw(), VisualScreenMask, &mut template, &mut count);
+        if list.is_null() {
+            return Vec::new();
+        }
+        let visuals = std::slice::from_raw_parts(list, count as usize).to_vec();
+        XFree(list as *mut _);
+        visuals
+    }
+}
+
+pub fn default_visual_id(display: Display, screen_number: i32) -> u64 {
+    unsafe { XVisualIDFromVisual(XDefaultVisual(display.raw(), screen_number)) }
+}
+
+pub fn create_colormap(display: Display, window: u64, visual: *mut Visual, alloc: i32) -> u64 {
+    unsafe { XCreateColormap(display.raw(), window, visual, alloc) }
+}
+
+pub fn store_colors(display: Display, colormap: u64, colours: &mut [XColor]) {
+    unsafe {
+        XStoreColors(
+            display.raw(),
+            colormap,
+            colours.as_mut_ptr(),
+            colours.len() as i32,
+        );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn create_window(
+    display: Display,
+    parent: u64,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    border_width: u32,
+    depth: i32,
+    visual: *mut Visual,
+    value_mask: u64,
+    attributes: &mut XSetWindowAttributes,
+) -> Result<u64, Error> {
+    let window = unsafe {
+        XCreateWindow(
+            display.raw(),
+            parent,
+            x,
+            y,
+            width,
+            height,
+            border_width,
+            depth,
+            CopyFromParent as u32,
+            visual,
+            value_mask,
+            attributes,
+        )
+    };
+    if window == 0 {
+        return Err(Error("XCreateWindow"));
+    }
+    Ok(window)
+}
+
+// a bare window the window manager leaves alone, for short lived popups
+pub fn create_popup(
+    display: Display,
+    parent: u64,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    background: u64,
+) -> Result<u64, Error> {
+    unsafe {
+        let window =
+            XCreateSimpleWindow(display.raw(), parent, x, y, width, height, 0, 0, background);
+        if window == 0 {
+            return Err(Error("XCreateSimpleWindow"));
+        }
+        let mut attributes: XSetWindowAttributes = std::mem::zeroed();
+        attributes.override_redirect = True;
+        XChangeWindowAttributes(display.raw(), window, CWOverrideRedirect, &mut attributes);
+        Ok(window)
+    }
+}
+
+pub fn destroy_window(display: Display, window: u64) {
+    unsafe {
+        XDestroyWindow(display.raw(), window);
+    }
+}
+
+pub fn create_pixmap(
+    display: Display,
+    drawable: u64,
+    width: u32,
+    height: u32,
+    depth: u32,
+) -> Result<u64, Error> {
+    let pixmap = unsafe { XCreatePixmap(display.raw(), drawable, width, height, depth) };
+    if pixmap == 0 {
+        return Err(Error("XCreatePixmap"));
+    }
+    Ok(pixmap)
+}
+
+pub fn free_pixmap(display: Display, pixmap: u64) {
+    unsafe {
+        XFreePixmap(display.raw(), pixmap);
+    }
+}
+
+pub fn set_standard_properties(display: Display, window: u64, window_name: &str, icon_name: &str) {
+    let window_name = c_string(window_name);
+    let icon_name = c_string(icon_name);
+
+    unsafe {
+        XSetStandardProperties(
+            display.raw(),
+            window,
+            window_name.as_ptr(),
+            icon_name.as_ptr(),
+            0,
+            std::ptr::null_mut(),
+            0,
+            std::ptr::null_mut(),
+        );
+    }
+}
+
+pub fn select_input(display: Display, window: u64, event_mask: i64) {
+    unsafe {
+        XSelectInput(display.raw(), window, event_mask);
+    }
+}
+
+pub fn create_gc(
+    display: Display,
+    drawable: u64,
+    value_mask: u64,
+    values: &mut XGCValues,
+) -> Result<Gc, Error> {
+    let gc = unsafe { XCreateGC(display.raw(), drawable, value_mask, values) };
+    NonNull::new(gc).map(Gc).ok_or(Error("XCreateGC"))
+}
+
+// every value left at the server's default
+pub fn create_default_gc(display: Display, drawable: u64) -> Result<Gc, Error> {
+    let gc = unsafe { XCreateGC(display.raw(), drawable, 0, std::ptr::null_mut()) };
+    NonNull::new(gc).map(Gc).ok_or(Error("XCreateGC"))
+}
+
+pub fn free_gc(display: Display, gc: Gc) {
+    unsafe {
+        XFreeGC(display.raw(), gc.raw());
+    }
+}
+
+// XC_crosshair from X11/cursorfont.h, the x11 crate doesn't carry the glyph table
+const XC_CROSSHAIR: u32 = 34;
+
+pub fn define_crosshair_cursor(display: Display, window: u64) {
+    unsafe {
+        let cursor = XCreateFontCursor(display.raw(), XC_CROSSHAIR);
+        XDefineCursor(display.raw(), window, cursor);
+        // the window keeps its own reference to the cursor
+        XFreeCursor(display.raw(), cursor);
+    }
+}
+
+pub fn set_foreground(display: Display, gc: Gc, pixel: u64) {
+    unsafe {
+        XSetForeground(display.raw(), gc.raw(), pixel);
+    }
+}
+
+// freed along with the display it was loaded on, so it has to be dropped
+// before the Connection is
+pub struct Font {
+    display: Display,
+    font: *mut XFontStruct,
+}
+
+impl Font {
+    pub fn load(display: Display, name: &str) -> Result<Self, Error> {
+        let name = c_string(name);
+        let font = unsafe { XLoadQueryFont(display.raw(), name.as_ptr()) };
+        if font.is_null() {
+            return Err(Error("XLoadQueryFont"));
+        }
+        Ok(Self { display, font })
+    }
+
+    pub fn ascent(&self) -> i32 {
+        unsafe { (*self.font).ascent }
+    }
+
+    pub fn descent(&self) -> i32 {
+        unsafe { (*self.font).descent }
+    }
+
+    pub fn text_width(&self, text: &str) -> i32 {
+        unsafe { XTextWidth(self.font, text.as_ptr() as *const _, text.len() as i32) }
+    }
+
+    pub fn set_on(&self, display: Display, gc: Gc) {
+        unsafe {
+            XSetFont(display.raw(), gc.raw(), (*self.font).fid);
+        }
+    }
+}
+
+impl Drop for Font {
+    fn drop(&mut self) {
+        unsafe {
+            XFreeFont(self.display.raw(), self.font);
+        }
+    }
+}
+
+pub struct Image(*mut XImage);
+
+impl Image {
+    pub fn width(&self) -> i32 {
+        unsafe { (*self.0).width }
+    }
+
+    pub fn height(&self) -> i32 {
+        unsafe { (*self.0).height }
+    }
+
+    // every pixel row by row, one u32 each. what XGetImage hands back is in
+    // the server's layout: a 24 bit visual can be packed into 3 bytes a pixel
+    // instead of 4, and the byte order is the server's, not ours
+    pub fn pixels(&self) -> Vec<u32> {
+        let (width, height) = (self.width(), self.height());
+        let mut pixels = Vec::with_capacity((width * height) as usize);
+
+        let Some((bytes_per_pixel, msb_first)) = self.layout() else {
+            for y in 0..height {
+                pixels.extend((0..width).map(|x| self.pixel(x, y) as u32));
+            }
+            return pixels;
+        };
+
+        let data = unsafe { std::slice::from_raw_parts((*self.0).data as *const u8, self.len()) };
+        for row in data.chunks_exact(self.stride()) {
+            let row = &row[..width as usize * bytes_per_pixel];
+            for pixel in row.chunks_exact(bytes_per_pixel) {
+                pixels.push(read_pixel(pixel, msb_first));
+            }
+        }
+        pixels
+    }
+
+    // rewrites every pixel in place, keeping the server's layout
+    pub fn map_pixels(&mut self, mut f: impl FnMut(u32) -> u32) {
+        let (width, height) = (self.width(), self.height());
+
+        let Some((bytes_per_pixel, msb_first)) = self.layout() else {
+            for y in 0..height {
+                for x in 0..width {
+                    let pixel = f(self.pixel(x, y) as u32);
+                    unsafe {
+                        XPutPixel(self.0, x, y, pixel as u64);
+                    }
+                }
+            }
+            return;
+        };
+
+        let data = unsafe { std::slice::from_raw_parts_mut((*self.0).data as *mut u8, self.len()) };
+        for row in data.chunks_exact_mut(self.stride()) {
+            let row = &mut row[..width as usize * bytes_per_pixel];
+            for pixel in row.chunks_exact_mut(bytes_per_pixel) {
+                write_pixel(pixel, f(read_pixel(pixel, msb_first)), msb_first);
+            }
+        }
+    }
+
+    // rows are bytes_per_line apart, and the server is free to pad them out
+    // past width * bytes per pixel. reading the data as one flat run of
+    // pixels shears the image whenever it does, so always step by this
+    fn stride(&self) -> usize {
+        unsafe { (*self.0).bytes_per_line as usize }
+    }
+
+    fn len(&self) -> usize {
+        self.stride() * self.height() as usize
+    }
+
+    // bytes per pixel and whether the most significant byte comes first.
+    // None for depths under a byte a pixel, those are left to Xlib
+    fn layout(&self) -> Option<(usize, bool)> {
+        let image = unsafe { &*self.0 };
+        if image.bits_per_pixel % 8 != 0 || image.bits_per_pixel > 32 || self.len() == 0 {
+            return None;
+        }
+        Some((
+            image.bits_per_pixel as usize / 8,
+            image.byte_order == MSBFirst,
+        ))
+    }
+
+    pub fn pixel(&self, x: i32, y: i32) -> u64 {
+        unsafe { XGetPixel(self.0, x, y) }
+    }
+}
+
+impl Drop for Image {
+    fn drop(&mut self) {
+        unsafe {
+            XDestroyImage(self.0);
+        }
+    }
+}
+
+// pixels come in the server's byte order, which over a remote connection
+// needn't be ours. from_be_bytes/from_le_bytes are a plain load when it
+// matches the host and a byte swap when it doesn't
+fn read_pixel(bytes: &[u8], msb_first: bool) -> u32 {
+    if let Ok(bytes) = <[u8; 4]>::try_from(bytes) {
+        return if msb_first {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        };
+    }
+    let byte = |value: u32, byte: &u8| value << 8 | *byte as u32;
+    if msb_first {
+        bytes.iter().fold(0, byte)
+    } else {
+        bytes.iter().rev().fold(0, byte)
+    }
+}
+
+fn write_pixel(bytes: &mut [u8], mut value: u32, msb_first: bool) {
+    if bytes.len() == 4 {
+        bytes.copy_from_slice(&if msb_first {
+            value.to_be_bytes()
+        } else {
+            value.to_le_bytes()
+        });
+        return;
+    }
+    let mut write = |byte: &mut u8| {
+        *byte = value as u8;
+        value >>= 8;
+    };
+    if msb_first {
+        bytes.iter_mut().rev().for_each(&mut write);
+    } else {
+        bytes.iter_mut().for_each(&mut write);
+    }
+}
+
+unsafe extern "C" fn ignore_error(_: *mut _XDisplay, _: *mut XErrorEvent) -> i32 {
+    0
+}
+
+pub fn get_image(
+    display: Display,
+    drawable: u64,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+) -> Result<Image, Error> {
+    // the null below is only ever seen with a handler of our own in place,
+    // Xlib's default one prints the BadMatch and exits the process first.
+    // anything still queued from earlier is flushed so it isn't swallowed too
+    let image = unsafe {
+        XSync(display.raw(), False);
+        let previous = XSetErrorHandler(Some(ignore_error));
+        let image = XGetImage(
+            display.raw(),
+            drawable,
+            x,
+            y,
+            width,
+            height,
+            XAllPlanes(),
+            ZPixmap,
+        );
+        XSetErrorHandler(previous);
+        image
+    };
+    if image.is_null() {
+        return Err(Error("XGetImage"));
+    }
+    Ok(Image(image))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn put_image(
+    display: Display,
+    drawable: u64,
+    gc: Gc,
+    image: &Image,
+    src_x: i32,
+    src_y: i32,
+    dest_x: i32,
+    dest_y: i32,
+    width: u32,
+    height: u32,
+) {
+    unsafe {
+        XPutImage(
+            display.raw(),
+            drawable,
+            gc.raw(),
+            image.0,
+            src_x,
+            src_y,
+            dest_x,
+            dest_y,
+            width,
+            height,
+        );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn copy_area(
+    display: Display,
+    src: u64,
+    dest: u64,
+    gc: Gc,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+) {
+    unsafe {
+        XCopyArea(
+            display.raw(),
+            src,
+            dest,
+            gc.raw(),
+            x,
+            y,
+            width,
+            height,
+            x,
+            y,
+        );
+    }
+}
+
+pub fn set_window_background_pixmap(display: Display, window: u64, pixmap: u64) {
+    unsafe {
+        XSetWindowBackgroundPixmap(display.raw(), window, pixmap);
+    }
+}
+
+pub fn set_window_background(display: Display, window: u64, pixel: u64) {
+    unsafe {
+        XSetWindowBackground(display.raw(), window, pixel);
+    }
+}
+
+// from X11/extensions/shape.h, the x11 crate has no bindings for XShape
+const SHAPE_SET: i32 = 0;
+const SHAPE_BOUNDING: i32 = 0;
+const SHAPE_INPUT: i32 = 2;
+const UNSORTED: i32 = 0;
+
+#[link(name = "Xext")]
+extern "C" {
+    fn XShapeCombineRectangles(
+        display: *mut _XDisplay,
+        dest: u64,
+        dest_kind: i32,
+        x_off: i32,
+        y_off: i32,
+        rectangles: *mut XRectangle,
+        n_rects: i32,
+        op: i32,
+        ordering: i32,
+    );
+    fn XShapeCombineMask(
+        display: *mut _XDisplay,
+        dest: u64,
+        dest_kind: i32,
+        x_off: i32,
+        y_off: i32,
+        src: u64,
+        op: i32,
+    );
+}
+
+// an empty input region lets every click fall through to the windows below,
+// setting the mask back to None restores the default of the whole window
+pub fn set_click_through(display: Display, window: u64, click_through: bool) {
+    unsafe {
+        if click_through {
+            XShapeCombineRectangles(
+                display.raw(),
+                window,
+                SHAPE_INPUT,
+                0,
+                0,
+                std::ptr::null_mut(),
+                0,
+                SHAPE_SET,
+                UNSORTED,
+            );
+        } else {
+            XShapeCombineMask(display.raw(), window, SHAPE_INPUT, 0, 0, 0, SHAPE_SET);
+        }
+    }
+}
+
+// only the given parts of the window are shown, everything else is whatever
+// is underneath it. rects are x, y, width, height
+pub fn set_visible_rects(display: Display, window: u64, rects: &[(i32, i32, u32, u32)]) {
+    let mut rectangles: Vec<XRectangle> = rects
+        .iter()
+        .map(|&(x, y, width, height)| XRectangle {
+            x: x as i16,
+            y: y as i16,
+            width: width as u16,
+            height: height as u16,
+        })
+        .collect();
+    unsafe {
+        XShapeCombineRectangles(
+            display.raw(),
+            window,
+            SHAPE_BOUNDING,
+            0,
+            0,
+            rectangles.as_mut_ptr(),
+            rectangles.len() as i32,
+            SHAPE_SET,
+            UNSORTED,
+        );
+    }
+}
+
+pub fn map_raised(display: Display, window: u64) {
+    unsafe {
+        XMapRaised(display.raw(), window);
+    }
+}
+
+pub fn unmap_window(display: Display, window: u64) {
+    unsafe {
+        XUnmapWindow(display.raw(), window);
+    }
+}
+
+pub fn set_input_focus(display: Display, window: u64) {
+    unsafe {
+        XSetInputFocus(display.raw(), window, RevertToNone, CurrentTime);
+    }
+}
+
+// confine_to keeps the pointer inside that window for as long as the grab lasts
+pub fn grab_pointer(
+    display: Display,
+    window: u64,
+    confine_to: u64,
+    event_mask: i64,
+) -> Result<(), Error> {
+    let status = unsafe {
+        XGrabPointer(
+            display.raw(),
+            window,
+            False,
+            event_mask as u32,
+            GrabModeAsync,
+            GrabModeAsync,
+            confine_to,
+            0,
+            CurrentTime,
+        )
+    };
+    if status != GrabSuccess {
+        return Err(Error("XGrabPointer"));
+    }
+    Ok(())
+}
+
+pub fn grab_keyboard(display: Display, window: u64) -> Result<(), Error> {
+    let status = unsafe {
+        XGrabKeyboard(
+            display.raw(),
+            window,
+            False,
+            GrabModeAsync,
+            GrabModeAsync,
+            CurrentTime,
+        )
+    };
+    if status != GrabSuccess {
+        return Err(Error("XGrabKeyboard"));
+    }
+    Ok(())
+}
+
+pub fn ungrab_pointer(display: Display) {
+    unsafe {
+        XUngrabPointer(display.raw(), CurrentTime);
+    }
+}
+
+pub fn ungrab_keyboard(display: Display) {
+    unsafe {
+        XUngrabKeyboard(display.raw(), CurrentTime);
+    }
+}
+
+// keysym names as in xev/xmodmap, e.g. "F12" or "Print"
+// doesn't need a connection, the names are fixed by the protocol
+pub fn keysym_from_name(name: &str) -> Result<u64, Error> {
+    let name = CString::new(name).map_err(|_| Error("XStringToKeysym"))?;
+    let keysym = unsafe { XStringToKeysym(name.as_ptr()) };
+    if keysym == 0 {
+        return Err(Error("XStringToKeysym"));
+    }
+    Ok(keysym)
+}
+
+pub fn keysym_to_keycode(display: Display, keysym: &str) -> Result<u8, Error> {
+    let keysym = keysym_from_name(keysym)?;
+
+    let keycode = unsafe { XKeysymToKeycode(display.raw(), keysym) };
+    if keycode == 0 {
+        return Err(Error("XKeysymToKeycode"));
+    }
+    Ok(keycode)
+}
+
+pub fn grab_key(display: Display, keycode: u8, window: u64) {
+    unsafe {
+        XGrabKey(
+            display.raw(),
+            keycode as i32,
+            AnyModifier,
+            window,
+            False,
+            GrabModeAsync,
+            GrabModeAsync,
+        );
+    }
+}
+
+pub fn ungrab_key(display: Display, keycode: u8, window: u64) {
+    unsafe {
+        XUngrabKey(display.raw(), keycode as i32, AnyModifier, window);
+    }
+}
+
+pub fn get_input_focus(display: Display) -> u64 {
+    let mut focus = 0;
+    let mut revert_to = 0;
+    unsafe {
+        XGetInputFocus(display.raw(), &mut focus, &mut revert_to);
+    }
+    focus
+}
+
+pub fn sync(display: Display) {
+    unsafe {
+        XSync(display.raw(), False);
+    }
+}
+
+pub fn draw_rectangle(display: Display, drawable: u64, gc: Gc, x: i32, y: i32, w: u32, h: u32) {
+    unsafe {
+        XDrawRectangle(display.raw(), drawable, gc.raw(), x, y, w, h);
+    }
+}
+
+pub fn fill_rectangle(display: Display, drawable: u64, gc: Gc, x: i32, y: i32, w: u32, h: u32) {
+    unsafe {
+        XFillRectangle(display.raw(), drawable, gc.raw(), x, y, w, h);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn draw_arc(
+    display: Display,
+    drawable: u64,
+    gc: Gc,
+    x: i32,
+    y: i32,
+    w: u32,
+    h: u32,
+    angle1: i32,
+    angle2: i32,
+) {
+    unsafe {
+        XDrawArc(
+            display.raw(),
+            drawable,
+            gc.raw(),
+            x,
+            y,
+            w,
+            h,
+            angle1,
+            angle2,
+        );
+    }
+}
+
+pub fn draw_line(display: Display, drawable: u64, gc: Gc, x1: i32, y1: i32, x2: i32, y2: i32) {
+    unsafe {
+        XDrawLine(display.raw(), drawable, gc.raw(), x1, y1, x2, y2);
+    }
+}
+
+pub fn draw_string(display: Display, drawable: u64, gc: Gc, x: i32, y: i32, text: &str) {
+    unsafe {
+        XDrawString(
+            display.raw(),
+            drawable,
+            gc.raw(),
+            x,
+            y,
+            text.as_ptr() as *const _,
+            text.len() as i32,
+        );
+    }
+}
+
+pub enum Event {
+    Motion {
+        x: i32,
+        y: i32,
+    },
+    ButtonPress {
+        button: u32,
+        x: i32,
+        y: i32,
+        state: u32,
+    },
+    ButtonRelease {
+        button: u32,
+        x: i32,
+        y: i32,
+    },
+    // keysym is from the unshifted column, so the layout decides what it is
+    KeyPress {
+        keycode: u32,
+        keysym: u64,
+        state: u32,
+    },
+    SelectionRequest(SelectionRequest),
+    SelectionClear,
+    // only deletions, the only change anything here waits for
+    PropertyDeleted {
+        window: u64,
+        atom: u64,
+    },
+    Other,
+}
+
+#[derive(Copy, Clone)]
+pub struct SelectionRequest {
+    pub requestor: u64,
+    pub selection: u64,
+    pub target: u64,
+    pub property: u64,
+    pub time: u64,
+}
+
+pub fn pending(display: Display) -> i32 {
+    unsafe { XPending(display.raw()) }
+}
+
+// blocks until the server has sent something or timeout has passed, with no
+// timeout it waits for as long as it takes
+pub fn wait_for_event(display: Display, timeout: Option<std::time::Duration>) {
+    unsafe {
+        if XPending(display.raw()) > 0 {
+            return;
+        }
+        let mut connection = libc::pollfd {
+            fd: XConnectionNumber(display.raw()),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let timeout = timeout.map_or(-1, |timeout| {
+            // rounded up so a wait of less than a millisecond doesn't spin
+            timeout
+                .as_micros()
+                .div_ceil(1000)
+                .min(libc::c_int::MAX as u128) as libc::c_int
+        });
+        libc::poll(&mut connection, 1, timeout);
+    }
+}
+
+pub fn next_event(display: Display) -> Event {
+    unsafe {
+        let mut event: XEvent = MaybeUninit::zeroed().assume_init();
+
+        XNextEvent(display.raw(), &mut event);
+        match event.type_ {
+            x11::xlib::MotionNotify => Event::Motion {
+                x: event.motion.x,
+                y: event.motion.y,
+            },
+            x11::xlib::ButtonPress => Event::ButtonPress {
+                button: event.button.button,
+                x: event.button.x,
+                y: event.button.y,
+                state: event.button.state,
+            },
+            x11::xlib::ButtonRelease => Event::ButtonRelease {
+                button: event.button.button,
+                x: event.button.x,
+                y: event.button.y,
+            },
+            x11::xlib::KeyPress => Event::KeyPress {
+                keycode: event.key.keycode,
+                keysym: XLookupKeysym(&mut event.key, 0),
+                state: event.key.state,
+            },
+            x11::xlib::SelectionRequest => Event::SelectionRequest(SelectionRequest {
+                requestor: event.selection_request.requestor,
+                selection: event.selection_request.selection,
+                target: event.selection_request.target,
+                property: event.selection_request.property,
+                time: event.selection_request.time,
+            }),
+            x11::xlib::SelectionClear => Event::SelectionClear,
+            x11::xlib::PropertyNotify if event.property.state == PropertyDelete => {
+                Event::PropertyDeleted {
+                    window: event.property.window,
+                    atom: event.property.atom,
+                }
+            }
+            _ => Event::Other,
+        }
+    }
+}
+
+// returns (root, parent)
+pub fn query_tree(display: Display, window: u64) -> Result<(u64, u64), Error> {
+    let mut root = 0;
+    let mut parent = 0;
+    let mut children = std::ptr::null_mut();
+    let mut nchildren = 0;
+
+    unsafe {
+        if XQueryTree(
+            display.raw(),
+            window,
+            &mut root,
+            &mut parent,
+            &mut children,
+            &mut nchildren,
+        ) == 0
+        {
+            return Err(Error("XQueryTree"));
+        }
+        if !children.is_null() {
+            XFree(children as *mut _);
+        }
+    }
+
+    Ok((root, parent))
+}
+
+// bottom to top in stacking order
+pub fn children(display: Display, window: u64) -> Result<Vec<u64>, Error> {
+    let mut root = 0;
+    let mut parent = 0;
+    let mut children = std::ptr::null_mut();
+    let mut nchildren = 0;
+
+    unsafe {
+        if XQueryTree(
+            display.raw(),
+            window,
+            &mut root,
+            &mut parent,
+            &mut children,
+            &mut nchildren,
+        ) == 0
+        {
+            return Err(Error("XQueryTree"));
+        }
+        if children.is_null() {
+            return Ok(Vec::new());
+        }
+
+        let list = std::slice::from_raw_parts(children, nchildren as usize).to_vec();
+        XFree(children as *mut _);
+        Ok(list)
+    }
+}
+
+pub fn translate_coordinates(
+    display: Display,
+    src: u64,
+    dest: u64,
+    x: i32,
+    y: i32,
+) -> Result<(i32, i32), Error> {
+    let mut dest_x = 0;
+    let mut dest_y = 0;
+    let mut child = 0;
+
+    let status = unsafe {
+        XTranslateCoordinates(
+            display.raw(),
+            src,
+            dest,
+            x,
+            y,
+            &mut dest_x,
+            &mut dest_y,
+            &mut child,
+        )
+    };
+    if status == 0 {
+        return Err(Error("XTranslateCoordinates"));
+    }
+    Ok((dest_x, dest_y))
+}
+
+// WM_NAME, None if the window doesn't have one
+pub fn fetch_name(display: Display, window: u64) -> Option<String> {
+    unsafe {
+        let mut name = std::ptr::null_mut();
+        if XFetchName(display.raw(), window, &mut name) == 0 || name.is_null() {
+            return None;
+        }
+        let owned = std::ffi::CStr::from_ptr(name)
+            .to_string_lossy()
+            .into_owned();
+        XFree(name as *mut _);
+        Some(owned)
+    }
+}
+
+// the class half of WM_CLASS, e.g. "Firefox"
+pub fn class_hint(display: Display, window: u64) -> Option<String> {
+    unsafe {
+        let mut hint: XClassHint = MaybeUninit::zeroed().assume_init();
+        if XGetClassHint(display.raw(), window, &mut hint) == 0 {
+            return None;
+        }
+
+        let class = (!hint.res_class.is_null()).then(|| {
+            std::ffi::CStr::from_ptr(hint.res_class)
+                .to_string_lossy()
+                .into_owned()
+        });
+        if !hint.res_name.is_null() {
+            XFree(hint.res_name as *mut _);
+        }
+        if !hint.res_class.is_null() {
+            XFree(hint.res_class as *mut _);
+        }
+        class
+    }
+}
+
+// a CARDINAL[] property such as _NET_FRAME_EXTENTS, None if it isn't set
+pub fn cardinal_property(display: Display, window: u64, name: &str) -> Option<Vec<u64>> {
+    format32_property(display, window, name, XA_CARDINAL)
+}
+
+// a WINDOW[] property such as _NET_CLIENT_LIST, None if it isn't set
+pub fn window_property(display: Display, window: u64, name: &str) -> Option<Vec<u64>> {
+    format32_property(display, window, name, XA_WINDOW)
+}
+
+fn format32_property(
+    display: Display,
+    window: u64,
+    name: &str,
+    property_type: Atom,
+) -> Option<Vec<u64>> {
+    let name = c_string(name);
+
+    unsafe {
+        let atom = XInternAtom(display.raw(), name.as_ptr(), True);
+        if atom == 0 {
+            return None;
+        }
+
+        let mut actual_type = 0;
+        let mut actual_format = 0;
+        let mut count = 0;
+        let mut remaining = 0;
+        let mut data = std::ptr::null_mut();
+
+        let status = XGetWindowProperty(
+            display.raw(),
+            window,
+            atom,
+            0,
+            i64::MAX / 4,
+            False,
+            property_type,
+            &mut actual_type,
+            &mut actual_format,
+            &mut count,
+            &mut remaining,
+            &mut data,
+        );
+        if status != Success as i32 || data.is_null() {
+            return None;
+        }
+
+        // format 32 properties come back as an array of longs whatever their size on the wire
+        let values = if actual_format == 32 {
+            Some(
+                std::slice::from_raw_parts(data as *const std::os::raw::c_ulong, count as usize)
+                    .to_vec(),
+            )
+        } else {
+            None
+        };
+        XFree(data as *mut _);
+        values
+    }
+}
+
+pub struct CursorImage {
+    // top left corner on the root, the hotspot already taken off
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    // premultiplied ARGB
+    pub pixels: Vec<u32>,
+}
+
+// the sprite currently shown for the pointer, errors without XFixes
+pub fn cursor_image(display: Display) -> Result<CursorImage, Error> {
+    unsafe {
+        let (mut event_base, mut error_base) = (0, 0);
+        if XFixesQueryExtension(display.raw(), &mut event_base, &mut error_base) == 0 {
+            return Err(Error("XFixesQueryExtension"));
+        }
+
+        let image = XFixesGetCursorImage(display.raw());
+        if image.is_null() {
+            return Err(Error("XFixesGetCursorImage"));
+        }
+
+        let (width, height) = ((*image).width as u32, (*image).height as u32);
+        // one pixel per long, only the low 32 bits are used
+        let pixels = std::slice::from_raw_parts((*image).pixels, (width * height) as usize)
+            .iter()
+            .map(|pixel| *pixel as u32)
+            .collect();
+        let cursor = CursorImage {
+            x: (*image).x as i32 - (*image).xhot as i32,
+            y: (*image).y as i32 - (*image).yhot as i32,
+            width,
+            height,
+            pixels,
+        };
+
+        XFree(image as *mut _);
+        Ok(cursor)
+    }
+}
+
+pub struct Crtc {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub rotation: u16,
+}
+
+// every enabled CRTC, empty if XRandR is unavailable
+pub fn crtcs(display: Display, root: u64) -> Vec<Crtc> {
+    let mut crtcs = Vec::new();
+
+    unsafe {
+        let resources = XRRGetScreenResourcesCurrent(display.raw(), root);
+        if resources.is_null() {
+            return crtcs;
+        }
+
+        let ids = std::slice::from_raw_parts((*resources).crtcs, (*resources).ncrtc as usize);
+        for &id in ids {
+            let info = XRRGetCrtcInfo(display.raw(), resources, id);
+            if info.is_null() {
+                continue;
+            }
+            if (*info).width != 0 && (*info).height != 0 {
+                crtcs.push(Crtc {
+                    x: (*info).x,
+                    y: (*info).y,
+                    width: (*info).width,
+                    height: (*info).height,
+                    rotation: (*info).rotation,
+                });
+            }
+            XRRFreeCrtcInfo(info);
+        }
+
+        XRRFreeScreenResources(resources);
+    }
+
+    crtcs
+}